@@ -1,4 +1,7 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    prost_build::compile_protos(&["proto/vehicles.proto"], &["proto/"])?;
+    // protoc is only needed to encode vehicle lists
+    if std::env::var_os("CARGO_FEATURE_SYTRAL").is_some() {
+        prost_build::compile_protos(&["proto/vehicles.proto"], &["proto/"])?;
+    }
     Ok(())
-}
+}
\ No newline at end of file
@@ -0,0 +1,109 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{FromJsonQueryResult, Set};
+use serde::{Serialize, Deserialize};
+use validator::{Validate, ValidationError};
+
+use crate::validation::{not_blank, MAX_NAME_LEN};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, Validate)]
+#[sea_orm(table_name = "api_key")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    /// Owner of the key, set from the authenticated user
+    #[serde(skip_deserializing)]
+    pub user_id: i32,
+    #[validate(length(min = 1, max = MAX_NAME_LEN), custom(function = not_blank))]
+    pub name: String,
+    /// First characters of the key, to tell keys apart without storing them
+    #[serde(skip_deserializing)]
+    pub prefix: String,
+    /// SHA-256 of the key, never serialized
+    #[sea_orm(unique)]
+    #[serde(skip)]
+    pub key_hash: String,
+    #[validate(custom(function = not_empty))]
+    pub scopes: Scopes,
+    /// The key is rejected from then on, it never expires when unset
+    #[serde(default)]
+    pub expires_at: Option<DateTimeUtc>,
+    #[serde(skip_deserializing)]
+    pub last_used_at: Option<DateTimeUtc>,
+    /// Set on insert by `before_save`
+    #[serde(skip_deserializing)]
+    pub created_at: DateTimeUtc,
+}
+
+/// What a request authenticated by an API key may do
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scope {
+    /// Create, update and delete messages
+    #[serde(rename = "message:write")]
+    MessageWrite,
+    /// Read users and channel members
+    #[serde(rename = "user:read")]
+    UserRead,
+    /// Everything, including user, channel and key management
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    /// Name of the scope, as serialized
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scope::MessageWrite => "message:write",
+            Scope::UserRead => "user:read",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+/// Scopes granted to a key, stored as a JSON array
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct Scopes(pub Vec<Scope>);
+
+impl Scopes {
+    /// `admin` grants every other scope.
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.0.iter().any(|granted| *granted == scope || *granted == Scope::Admin)
+    }
+}
+
+fn not_empty(scopes: &Scopes) -> Result<(), ValidationError> {
+    if scopes.0.is_empty() {
+        return Err(ValidationError::new("length").with_message("must grant at least one scope".into()));
+    }
+    Ok(())
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,65 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::Set;
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "channel")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub name: String,
+    /// Set on insert by `before_save`
+    #[serde(skip_deserializing)]
+    pub created_at: DateTimeUtc,
+    /// Set on every save by `before_save`
+    #[serde(skip_deserializing)]
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::channel_member::Entity")]
+    ChannelMember,
+    #[sea_orm(has_many = "super::message::Entity")]
+    Message,
+}
+
+impl Related<super::channel_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChannelMember.def()
+    }
+}
+
+impl Related<super::message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Message.def()
+    }
+}
+
+/// Members of the channel
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::channel_member::Relation::User.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::channel_member::Relation::Channel.def().rev())
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = chrono::Utc::now();
+        if insert {
+            self.created_at = Set(now);
+        }
+        self.updated_at = Set(now);
+        Ok(self)
+    }
+}
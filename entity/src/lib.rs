@@ -1,2 +1,6 @@
+pub mod api_key;
+pub mod channel;
+pub mod channel_member;
 pub mod message;
 pub mod user;
+pub mod validation;
@@ -1,19 +1,100 @@
-//! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
-
 use sea_orm::entity::prelude::*;
+use sea_orm::Set;
 use serde::{Serialize, Deserialize};
+use validator::Validate;
+
+use crate::validation::{not_blank, MAX_CLIENT_MSG_ID_LEN, MAX_TEXT_LEN};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, Validate)]
 #[sea_orm(table_name = "message")]
 pub struct Model {
     #[sea_orm(primary_key)]
     #[serde(skip_deserializing)]
     pub id: i32,
+    #[validate(length(min = 1, max = MAX_TEXT_LEN), custom(function = not_blank))]
     pub text: String,
+    /// Taken from the session on `POST /api/message`, required elsewhere
+    #[serde(default)]
     pub user_id: i32,
+    /// Producer supplied idempotency key, a message is stored once per author and key
+    #[validate(length(min = 1, max = MAX_CLIENT_MSG_ID_LEN))]
+    pub client_msg_id: Option<String>,
+    /// The message this one replies to
+    pub parent_id: Option<i32>,
+    /// Channel of the message, `None` for the global stream
+    pub channel_id: Option<i32>,
+    /// How the message reached the server, `None` for seeded messages and those stored before sources were recorded
+    #[serde(skip_deserializing)]
+    pub source: Option<Source>,
+    /// Set on insert by `before_save`
+    #[serde(skip_deserializing)]
+    pub created_at: DateTimeUtc,
+    /// Set on every save by `before_save`
+    #[serde(skip_deserializing)]
+    pub updated_at: DateTimeUtc,
+}
+
+/// Ingestion path of a message
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// `POST /api/message` and `POST /api/message/batch`
+    #[sea_orm(string_value = "http")]
+    Http,
+    /// The Kafka consumer
+    #[sea_orm(string_value = "kafka")]
+    Kafka,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    /// Deleting an author with messages is refused, the server removes or reassigns them first
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id",
+        on_delete = "Restrict"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "Entity",
+        from = "Column::ParentId",
+        to = "Column::Id"
+    )]
+    Parent,
+    #[sea_orm(
+        belongs_to = "super::channel::Entity",
+        from = "Column::ChannelId",
+        to = "super::channel::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Channel,
+}
+
+impl Related<super::channel::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Channel.def()
+    }
+}
 
-impl ActiveModelBehavior for ActiveModel {}
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = chrono::Utc::now();
+        if insert {
+            self.created_at = Set(now);
+        }
+        self.updated_at = Set(now);
+        Ok(self)
+    }
+}
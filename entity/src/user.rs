@@ -1,20 +1,100 @@
-//! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
-
 use sea_orm::entity::prelude::*;
+use sea_orm::Set;
 use serde::{Serialize, Deserialize};
+use validator::Validate;
+
+use crate::validation::{not_blank, MAX_EMAIL_LEN, MAX_NAME_LEN};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, Validate)]
 #[sea_orm(table_name = "user")]
 pub struct Model {
     #[sea_orm(primary_key)]
     #[serde(skip_deserializing)]
     pub id: i32,
+    #[validate(length(min = 1, max = MAX_NAME_LEN), custom(function = not_blank))]
     pub name: String,
     #[sea_orm(unique)]
+    #[validate(email, length(max = MAX_EMAIL_LEN))]
     pub email: String,
+    /// PHC string of the password, never serialized
+    #[serde(skip)]
+    pub password_hash: Option<String>,
+    /// Set on creation, then changed with `PUT /api/user/{id}/role`
+    #[serde(default)]
+    pub role: Role,
+    /// The shared "Deleted user" holding the messages of deleted users, which cannot be edited
+    #[serde(skip_deserializing)]
+    pub tombstone: bool,
+    /// Bumped to revoke every token issued to the user, see `wamserver::auth::Claims`
+    #[serde(skip)]
+    pub token_version: i32,
+    /// Set on insert by `before_save`
+    #[serde(skip_deserializing)]
+    pub created_at: DateTimeUtc,
+    /// Set on every save by `before_save`
+    #[serde(skip_deserializing)]
+    pub updated_at: DateTimeUtc,
+}
+
+/// What a user may do, on top of reading public data and watching the WebSocket stream
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Also manages users, channels and the server configuration
+    #[sea_orm(string_value = "admin")]
+    Admin,
+    /// Posts and edits messages
+    #[default]
+    #[sea_orm(string_value = "member")]
+    Member,
+    /// Only reads
+    #[sea_orm(string_value = "read_only")]
+    ReadOnly,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::message::Entity")]
+    Message,
+    #[sea_orm(has_many = "super::channel_member::Entity")]
+    ChannelMember,
+}
 
-impl ActiveModelBehavior for ActiveModel {}
+impl Related<super::message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Message.def()
+    }
+}
+
+impl Related<super::channel_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChannelMember.def()
+    }
+}
+
+/// Channels the user is a member of
+impl Related<super::channel::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::channel_member::Relation::Channel.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::channel_member::Relation::User.def().rev())
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = chrono::Utc::now();
+        if insert {
+            self.created_at = Set(now);
+        }
+        self.updated_at = Set(now);
+        Ok(self)
+    }
+}
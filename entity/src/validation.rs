@@ -0,0 +1,18 @@
+//! Rules shared by the `Validate` implementations of the entities.
+
+use validator::ValidationError;
+
+pub const MAX_NAME_LEN: u64 = 100;
+pub const MAX_EMAIL_LEN: u64 = 254;
+pub const MAX_TEXT_LEN: u64 = 4096;
+pub const MAX_CLIENT_MSG_ID_LEN: u64 = 255;
+pub const MIN_PASSWORD_LEN: u64 = 8;
+pub const MAX_PASSWORD_LEN: u64 = 128;
+
+/// Reject strings made of whitespace only, `length` already rejects empty ones.
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if !value.is_empty() && value.trim().is_empty() {
+        return Err(ValidationError::new("blank").with_message("must not be blank".into()));
+    }
+    Ok(())
+}
@@ -71,11 +71,14 @@ function App() {
     const fetchData = async () => {
       try {
         const [usersResponse, messagesResponse] = await Promise.all([
-          axios.get('/api/user'),
-          axios.get('/api/message')
+          // Users are only listed to signed-in callers
+          axios.get('/api/user', { params: { limit: 1000 } })
+            .catch(err => err.response?.status === 401 ? { data: { users: [] } } : Promise.reject(err)),
+          axios.get('/api/message', { params: { limit: 200 } })
         ]);
-        setUsers(usersResponse.data);
-        setMessages(messagesResponse.data);
+        setUsers(usersResponse.data.users);
+        // Latest page only, newer messages arrive over the WebSocket
+        setMessages(messagesResponse.data.messages);
         setLoading(false);
       } catch (err) {
         setError(err.message);
@@ -10,12 +10,33 @@ import {
 } from '@mui/material';
 import * as wasm from "@yarichard/wam_message_gatling";
 
+// The WASM client sends its requests through window.fetch, so the key is added there for the duration of a run
+async function withApiKey(apiKey, serverUrl, run) {
+  if (!apiKey) {
+    return run();
+  }
+  const originalFetch = window.fetch;
+  window.fetch = (input, init) => {
+    const request = new Request(input, init);
+    if (request.url.startsWith(serverUrl)) {
+      request.headers.set('X-Api-Key', apiKey);
+    }
+    return originalFetch(request);
+  };
+  try {
+    return await run();
+  } finally {
+    window.fetch = originalFetch;
+  }
+}
+
 function Gatling() {
   // Gatling WASM state
   const [gatlingConfig, setGatlingConfig] = useState({
     messagesNb: 10,
     msgSec: 5,
-    serverUrl: window.location.origin
+    serverUrl: window.location.origin,
+    apiKey: ''
   });
   const [gatlingLoading, setGatlingLoading] = useState(false);
   const [gatlingResult, setGatlingResult] = useState(null);
@@ -28,10 +49,12 @@ function Gatling() {
 
     try {
       // Execute the gatling test
-      const result = await wasm.gatling_execute_standalone(
-        gatlingConfig.messagesNb,
-        gatlingConfig.msgSec,
-        gatlingConfig.serverUrl
+      const result = await withApiKey(gatlingConfig.apiKey, gatlingConfig.serverUrl, () =>
+        wasm.gatling_execute_standalone(
+          gatlingConfig.messagesNb,
+          gatlingConfig.msgSec,
+          gatlingConfig.serverUrl
+        )
       );
       
       setGatlingResult(result);
@@ -89,6 +112,22 @@ function Gatling() {
           placeholder="http://localhost:3000"
         />
       </Box>
+
+      <Box sx={{ mb: 2 }}>
+        <TextField
+          label="API Key"
+          type="password"
+          value={gatlingConfig.apiKey}
+          onChange={(e) => setGatlingConfig(prev => ({
+            ...prev,
+            apiKey: e.target.value
+          }))}
+          fullWidth
+          size="small"
+          placeholder="wam_..."
+          helperText="Key with the message:write scope, the server rejects anonymous messages"
+        />
+      </Box>
       
       <Button
         variant="contained"
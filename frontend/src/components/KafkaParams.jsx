@@ -14,7 +14,7 @@ function KafkaParams() {
         setKafkaParams(response.data);
         setLoading(false);
       } catch (err) {
-        setError(err.message);
+        setError(`Error: ${err.message}`);
         setLoading(false);
       }
     };
@@ -39,19 +39,33 @@ function KafkaParams() {
         <Typography variant="h5" gutterBottom>
           Kafka Configuration
         </Typography>
-        <Typography color="error">Error: {error}</Typography>
+        <Typography color="error">{error}</Typography>
       </Paper>
     );
   }
 
+  // Older servers do not report integration status, consider them enabled
+  const kafkaEnabled = kafkaParams.kafka_enabled !== false;
+  const sytralEnabled = kafkaParams.sytral_enabled !== false;
+  // The connection details are only sent to administrators
+  const restricted = kafkaParams.kafka_url === undefined;
+
   return (
     <Paper elevation={2} sx={{ p: 3, flex: 1 }}>
       <Typography variant="h5" gutterBottom>
         Kafka Configuration
       </Typography>
-      <Typography><strong>URL:</strong> {kafkaParams.kafka_url}</Typography>
-      <Typography><strong>Topic:</strong> {kafkaParams.kafka_topic}</Typography>
-      <Typography><strong>Group:</strong> {kafkaParams.kafka_group}</Typography>
+      <Typography><strong>Kafka:</strong> {kafkaEnabled ? 'Enabled' : 'Disabled'}</Typography>
+      <Typography><strong>SYTRAL:</strong> {sytralEnabled ? 'Enabled' : 'Disabled'}</Typography>
+      {restricted ? (
+        <Typography color="text.secondary">Only administrators can see the Kafka connection details</Typography>
+      ) : (
+        <>
+          <Typography><strong>URL:</strong> {kafkaParams.kafka_url}</Typography>
+          <Typography><strong>Topic:</strong> {kafkaParams.kafka_topic}</Typography>
+          <Typography><strong>Group:</strong> {kafkaParams.kafka_group}</Typography>
+        </>
+      )}
     </Paper>
   );
 }
@@ -3,27 +3,46 @@ import { Paper, Typography, Box, Switch, FormControlLabel } from '@mui/material'
 import { LineChart } from '@mui/x-charts/LineChart';
 import { useWebSocket } from '../contexts/WebSocketContext';
 
+const formatTime = (date) => date.toLocaleTimeString('en-US', {
+  hour12: false,
+  hour: '2-digit',
+  minute: '2-digit',
+  second: '2-digit'
+});
+
 export default function MessageRateChart() {
-  const { messages } = useWebSocket();
+  const { messages, stats } = useWebSocket();
   const [seriesData, setSeriesData] = useState(Array(60).fill(0));
   const [timeLabels, setTimeLabels] = useState(Array(60).fill(''));
   const [autoRefresh, setAutoRefresh] = useState(true);
-  const messagesLengthRef = useRef(messages.length);
+  const messagesRef = useRef(messages);
   const lastCountRef = useRef(messages.length);
+  const hasStatsRef = useRef(false);
 
   // Update ref when messages change
   useEffect(() => {
-    messagesLengthRef.current = messages.length;
+    messagesRef.current = messages;
   }, [messages]);
 
+  // The server pushes the per second rate of the last minute, it survives page reloads
+  useEffect(() => {
+    if (!stats || !autoRefresh) return;
+    hasStatsRef.current = true;
+    const counts = stats.rate.counts.slice(-60);
+    setSeriesData(counts.map(bucket => bucket.count));
+    setTimeLabels(counts.map(bucket => formatTime(new Date(bucket.start))));
+  }, [stats, autoRefresh]);
+
   useEffect(() => {
     // Initialize refs
-    lastCountRef.current = messagesLengthRef.current;
+    lastCountRef.current = messagesRef.current.length;
 
     const interval = setInterval(() => {
-      if (!autoRefresh) return;
+      // Count in the browser only until the server sends its stats
+      if (!autoRefresh || hasStatsRef.current) return;
       
-      const currentCount = messagesLengthRef.current;
+      const now = new Date();
+      const currentCount = messagesRef.current.length;
       let rate = currentCount - lastCountRef.current;
       
       // Handle case where messages might be cleared
@@ -31,13 +50,16 @@ export default function MessageRateChart() {
       
       lastCountRef.current = currentCount;
 
-      const now = new Date();
-      const timeLabel = now.toLocaleTimeString('en-US', { 
-        hour12: false, 
-        hour: '2-digit', 
-        minute: '2-digit', 
-        second: '2-digit' 
-      });
+      // Prefer the server timestamps over the arrival time when messages have them
+      if (messagesRef.current.some(msg => msg.created_at)) {
+        const windowStart = now.getTime() - 1000;
+        rate = messagesRef.current.filter(msg => {
+          const createdAt = Date.parse(msg.created_at);
+          return createdAt > windowStart && createdAt <= now.getTime();
+        }).length;
+      }
+
+      const timeLabel = formatTime(now);
 
       setSeriesData(prev => {
         const newData = [...prev.slice(1), rate];
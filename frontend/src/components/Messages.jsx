@@ -32,6 +32,11 @@ function Messages({ messages: initialMessages }) {
       headerName: 'User ID',
       width: 130,
     },
+    {
+      field: 'parent_id',
+      headerName: 'Reply to',
+      width: 110,
+    },
   ];
 
   return (
@@ -1,7 +1,20 @@
-import React from 'react';
+import React, { useEffect, useRef } from 'react';
 import { Typography, Paper, Stack } from '@mui/material';
+import { useWebSocket } from '../contexts/WebSocketContext';
+
+function Users({ users: initialUsers }) {
+  const { users, setUsers } = useWebSocket();
+  const seeded = useRef(false);
+
+  useEffect(() => {
+    // Initialize users with the ones from props once, later changes arrive over the WebSocket,
+    // so the list may be emptied by deletions without being filled again
+    if (!seeded.current && initialUsers && initialUsers.length > 0) {
+      seeded.current = true;
+      setUsers(initialUsers);
+    }
+  }, [initialUsers, setUsers]);
 
-function Users({ users }) {
   return (
     <Stack spacing={2}>
       {users.map(user => (
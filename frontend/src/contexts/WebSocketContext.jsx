@@ -7,6 +7,8 @@ export function WebSocketProvider({ children }) {
   const [socket, setSocket] = useState(null);
   const [messages, setMessages] = useState([]);
   const [vehicles, setVehicles] = useState([]);
+  const [users, setUsers] = useState([]);
+  const [stats, setStats] = useState(null);
 
   useEffect(() => {
     // Create WebSocket connection using config
@@ -33,6 +35,34 @@ export function WebSocketProvider({ children }) {
             // Add new message at the beginning
             return [newMessage, ...prev];
           });
+        } else if (data.msg_type === 'message_batch') {
+          setMessages(prev => {
+            const known = new Set(prev.map(msg => msg.id));
+            // Batches are stored oldest first, the list is newest first
+            const added = data.message.messages.filter(msg => !known.has(msg.id)).reverse();
+            return [...added, ...prev];
+          });
+        } else if (data.msg_type === 'message_updated') {
+          // Replace the edited message in place
+          setMessages(prev => prev.map(msg => msg.id === data.message.id ? data.message : msg));
+        } else if (data.msg_type === 'message_deleted') {
+          setMessages(prev => prev.filter(msg => msg.id !== data.message.id));
+        } else if (data.msg_type === 'user_created') {
+          setUsers(prev => prev.some(user => user.id === data.message.id) ? prev : [...prev, data.message]);
+        } else if (data.msg_type === 'user_updated') {
+          // Events leave out emails, keep the ones already loaded
+          setUsers(prev => prev.map(user => user.id === data.message.id ? { ...user, ...data.message } : user));
+        } else if (data.msg_type === 'user_deleted') {
+          const { id, messages, reassigned_to } = data.message;
+          setUsers(prev => prev.filter(user => user.id !== id));
+          // Their messages went with them, or to the tombstone user
+          if (messages === 'cascade') {
+            setMessages(prev => prev.filter(msg => msg.user_id !== id));
+          } else if (messages === 'tombstone') {
+            setMessages(prev => prev.map(msg => msg.user_id === id ? { ...msg, user_id: reassigned_to } : msg));
+          }
+        } else if (data.msg_type === 'stats') {
+          setStats(data.message);
         } else if (data.msg_type === 'sytral') {
           setVehicles(data.message.vehicles);
         }
@@ -62,7 +92,10 @@ export function WebSocketProvider({ children }) {
     messages,
     setMessages,
     vehicles,
-    setVehicles
+    setVehicles,
+    users,
+    setUsers,
+    stats
   };
 
   return (
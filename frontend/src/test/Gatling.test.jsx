@@ -129,4 +129,27 @@ describe('Gatling Component', () => {
       expect(screen.getByText('Second result')).toBeInTheDocument();
     });
   });
+
+  it('sends the API key with the requests of the run', async () => {
+    const originalFetch = window.fetch;
+    const fetchMock = vi.fn().mockResolvedValue(new Response('{}'));
+    window.fetch = fetchMock;
+    wasm.gatling_execute_standalone.mockImplementation(async (_nb, _rate, serverUrl) => {
+      await window.fetch(`${serverUrl}/api/message`, { method: 'POST' });
+      return 'Done';
+    });
+
+    const user = userEvent.setup();
+    render(<Gatling />);
+
+    await user.type(screen.getByLabelText('API Key'), 'wam_secret');
+    await user.click(screen.getByRole('button', { name: /Run Gatling Test/i }));
+
+    await waitFor(() => {
+      expect(screen.getByText('Done')).toBeInTheDocument();
+    });
+    expect(fetchMock.mock.calls[0][0].headers.get('X-Api-Key')).toBe('wam_secret');
+    expect(window.fetch).toBe(fetchMock);
+    window.fetch = originalFetch;
+  });
 });
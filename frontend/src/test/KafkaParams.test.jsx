@@ -47,6 +47,28 @@ describe('KafkaParams Component', () => {
     expect(screen.getByText(/consumer-group-1/)).toBeInTheDocument();
   });
 
+  it('displays which integrations are enabled', async () => {
+    const mockKafkaParams = {
+      kafka_enabled: false,
+      sytral_enabled: true,
+      kafka_url: 'localhost:9092',
+      kafka_topic: 'messages',
+      kafka_group: 'consumer-group-1'
+    };
+
+    axios.get.mockResolvedValue({ data: mockKafkaParams });
+
+    render(<KafkaParams />);
+
+    await waitFor(() => {
+      expect(screen.getByText('Disabled')).toBeInTheDocument();
+    });
+
+    expect(screen.getByText(/Kafka:/)).toBeInTheDocument();
+    expect(screen.getByText(/SYTRAL:/)).toBeInTheDocument();
+    expect(screen.getByText('Enabled')).toBeInTheDocument();
+  });
+
   it('displays error message when fetch fails', async () => {
     axios.get.mockRejectedValue(new Error('Network error'));
 
@@ -59,6 +81,20 @@ describe('KafkaParams Component', () => {
     expect(screen.getByText('Kafka Configuration')).toBeInTheDocument();
   });
 
+  it('shows the integration status without the connection details to non-admins', async () => {
+    axios.get.mockResolvedValue({ data: { kafka_enabled: true, sytral_enabled: false } });
+
+    render(<KafkaParams />);
+
+    await waitFor(() => {
+      expect(screen.getByText('Only administrators can see the Kafka connection details')).toBeInTheDocument();
+    });
+
+    expect(screen.getByText('Enabled')).toBeInTheDocument();
+    expect(screen.getByText('Disabled')).toBeInTheDocument();
+    expect(screen.queryByText(/URL:/)).not.toBeInTheDocument();
+  });
+
   it('calls the correct API endpoint', async () => {
     const mockKafkaParams = {
       kafka_url: 'localhost:9092',
@@ -128,6 +128,22 @@ describe('MessageRateChart Component', () => {
     expect(seriesData[0].label).toBe('Messages/sec');
   });
 
+  it('shows the rate pushed by the server', () => {
+    const counts = Array.from({ length: 60 }, (_, i) => ({
+      start: new Date(Date.UTC(2025, 0, 1, 0, 0, i)).toISOString(),
+      count: i % 3
+    }));
+    WebSocketContext.useWebSocket.mockReturnValue({
+      messages: [],
+      stats: { nb: 60, rate: { bucket: 'second', counts } }
+    });
+
+    render(<MessageRateChart />);
+
+    const seriesData = JSON.parse(screen.getByTestId('chart-series').textContent);
+    expect(seriesData[0].data).toEqual(counts.map(bucket => bucket.count));
+  });
+
   it('has correct chart configuration', () => {
     WebSocketContext.useWebSocket.mockReturnValue({
       messages: []
@@ -1,13 +1,24 @@
-import { describe, it, expect, vi } from 'vitest';
+import { describe, it, expect, vi, beforeEach } from 'vitest';
 import { render, screen } from '@testing-library/react';
 import Users from '../components/Users';
+import * as WebSocketContext from '../contexts/WebSocketContext';
+
+// Mock the WebSocket context
+vi.mock('../contexts/WebSocketContext', () => ({
+  useWebSocket: vi.fn()
+}));
 
 describe('Users Component', () => {
+  beforeEach(() => {
+    vi.clearAllMocks();
+  });
+
   it('renders users list correctly', () => {
     const mockUsers = [
       { id: 1, name: 'Alice' },
       { id: 2, name: 'Bob' }
     ];
+    WebSocketContext.useWebSocket.mockReturnValue({ users: mockUsers, setUsers: vi.fn() });
 
     render(<Users users={mockUsers} />);
 
@@ -18,6 +29,8 @@ describe('Users Component', () => {
   });
 
   it('renders empty list when no users provided', () => {
+    WebSocketContext.useWebSocket.mockReturnValue({ users: [], setUsers: vi.fn() });
+
     const { container } = render(<Users users={[]} />);
     const papers = container.querySelectorAll('.MuiPaper-root');
     expect(papers).toHaveLength(0);
@@ -29,9 +42,43 @@ describe('Users Component', () => {
       { id: 2, name: 'User 2' },
       { id: 3, name: 'User 3' }
     ];
+    WebSocketContext.useWebSocket.mockReturnValue({ users: mockUsers, setUsers: vi.fn() });
 
     const { container } = render(<Users users={mockUsers} />);
     const papers = container.querySelectorAll('.MuiPaper-root');
     expect(papers).toHaveLength(3);
   });
+
+  it('seeds the live list with the users it is given', () => {
+    const mockUsers = [{ id: 1, name: 'Alice' }];
+    const setUsers = vi.fn();
+    WebSocketContext.useWebSocket.mockReturnValue({ users: [], setUsers });
+
+    render(<Users users={mockUsers} />);
+
+    expect(setUsers).toHaveBeenCalledWith(mockUsers);
+  });
+
+  it('shows users received over the WebSocket', () => {
+    WebSocketContext.useWebSocket.mockReturnValue({ users: [{ id: 7, name: 'Carol' }], setUsers: vi.fn() });
+
+    render(<Users users={[]} />);
+
+    expect(screen.getByText('Carol')).toBeInTheDocument();
+  });
+
+  it('does not seed the list again once it has been emptied', () => {
+    const mockUsers = [{ id: 1, name: 'Alice' }];
+    const setUsers = vi.fn();
+    WebSocketContext.useWebSocket.mockReturnValue({ users: mockUsers, setUsers });
+
+    const { rerender } = render(<Users users={mockUsers} />);
+    expect(setUsers).toHaveBeenCalledTimes(1);
+
+    // The last user is deleted over the WebSocket
+    WebSocketContext.useWebSocket.mockReturnValue({ users: [], setUsers });
+    rerender(<Users users={mockUsers} />);
+
+    expect(setUsers).toHaveBeenCalledTimes(1);
+  });
 });
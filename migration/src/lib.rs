@@ -3,6 +3,20 @@ pub use sea_orm_migration::prelude::*;
 mod m20220101_000001_create_table;
 mod m20250730_084451_add_user;
 mod m20250730_090031_add_user_id;
+mod m20251018_000001_add_timestamps;
+mod m20251018_000002_add_message_user_fk;
+mod m20251018_000003_add_message_search;
+mod m20251018_000004_add_message_client_msg_id;
+mod m20251018_000005_add_message_parent_id;
+mod m20251018_000006_add_channels;
+mod m20251018_000007_add_user_password;
+mod m20251018_000008_add_api_keys;
+mod m20251018_000009_add_user_role;
+mod m20251018_000010_scope_client_msg_id;
+mod m20251018_000011_add_message_source;
+mod m20251018_000012_add_user_tombstone;
+mod m20251018_000013_add_user_token_version;
+mod m20251018_000014_require_timestamps;
 
 pub struct Migrator;
 
@@ -13,6 +27,20 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20250730_084451_add_user::Migration),
             Box::new(m20250730_090031_add_user_id::Migration),
+            Box::new(m20251018_000001_add_timestamps::Migration),
+            Box::new(m20251018_000002_add_message_user_fk::Migration),
+            Box::new(m20251018_000003_add_message_search::Migration),
+            Box::new(m20251018_000004_add_message_client_msg_id::Migration),
+            Box::new(m20251018_000005_add_message_parent_id::Migration),
+            Box::new(m20251018_000006_add_channels::Migration),
+            Box::new(m20251018_000007_add_user_password::Migration),
+            Box::new(m20251018_000008_add_api_keys::Migration),
+            Box::new(m20251018_000009_add_user_role::Migration),
+            Box::new(m20251018_000010_scope_client_msg_id::Migration),
+            Box::new(m20251018_000011_add_message_source::Migration),
+            Box::new(m20251018_000012_add_user_tombstone::Migration),
+            Box::new(m20251018_000013_add_user_token_version::Migration),
+            Box::new(m20251018_000014_require_timestamps::Migration),
         ]
     }
 }
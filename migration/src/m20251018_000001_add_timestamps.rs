@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in [Message::Table.into_iden(), User::Table.into_iden()] {
+            // SQLite only adds one column per statement, and without a non-constant default
+            for column in [Timestamps::CreatedAt, Timestamps::UpdatedAt] {
+                manager
+                .alter_table(sea_query::Table::alter()
+                    .table(table.clone())
+                    .add_column(ColumnDef::new(column).timestamp_with_time_zone())
+                    .to_owned()
+                )
+                .await?;
+            }
+
+            // Existing rows have no history, date them from the migration. The value is bound
+            // rather than using CURRENT_TIMESTAMP so that SQLite stores the same text format as new rows.
+            let now = chrono::Utc::now();
+            manager
+            .exec_stmt(Query::update()
+                .table(table.clone())
+                .value(Timestamps::CreatedAt, now)
+                .value(Timestamps::UpdatedAt, now)
+                .to_owned()
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in [Message::Table.into_iden(), User::Table.into_iden()] {
+            for column in [Timestamps::CreatedAt, Timestamps::UpdatedAt] {
+                manager
+                .alter_table(sea_query::Table::alter()
+                    .table(table.clone())
+                    .drop_column(column)
+                    .to_owned()
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+}
+
+#[derive(DeriveIden)]
+enum Timestamps {
+    CreatedAt,
+    UpdatedAt,
+}
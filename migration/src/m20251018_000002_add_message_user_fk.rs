@@ -0,0 +1,148 @@
+use sea_orm_migration::{prelude::*, schema::*, sea_orm::DatabaseBackend};
+
+const FK_NAME: &str = "fk_message_user";
+/// Must match `TOMBSTONE_NAME` and `TOMBSTONE_EMAIL` of the server
+const TOMBSTONE_NAME: &str = "Deleted user";
+const TOMBSTONE_EMAIL: &str = "deleted-user@wam.invalid";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Messages of users that no longer exist could not satisfy the constraint, they are handed
+        // over to the tombstone user, created if needed
+        let orphans = Query::select()
+            .column(Message::Id)
+            .from(Message::Table)
+            .and_where(Expr::col(Message::UserId).not_in_subquery(
+                Query::select().column(User::Id).from(User::Table).to_owned()
+            ))
+            .to_owned();
+        let tombstone = Query::select()
+            .column(User::Id)
+            .from(User::Table)
+            .and_where(Expr::col(User::Email).eq(TOMBSTONE_EMAIL))
+            .to_owned();
+        let now = chrono::Utc::now();
+        manager
+        .exec_stmt(Query::insert()
+            .into_table(User::Table)
+            .columns([User::Name, User::Email, User::CreatedAt, User::UpdatedAt])
+            .select_from(Query::select()
+                .exprs([Expr::val(TOMBSTONE_NAME), Expr::val(TOMBSTONE_EMAIL), Expr::val(now), Expr::val(now)])
+                .and_where(Expr::exists(orphans.clone()))
+                .and_where(Expr::exists(tombstone.clone()).not())
+                .to_owned()
+            )
+            .map_err(|e| DbErr::Migration(e.to_string()))?
+            .to_owned()
+        )
+        .await?;
+        manager
+        .exec_stmt(Query::update()
+            .table(Message::Table)
+            .value(Message::UserId, SimpleExpr::SubQuery(None, Box::new(tombstone.into_sub_query_statement())))
+            .and_where(Expr::col(Message::Id).in_subquery(orphans))
+            .to_owned()
+        )
+        .await?;
+
+        // Deleting a user is always restricted, the server applies `on_user_delete` itself
+        let on_delete = ForeignKeyAction::Restrict;
+        match manager.get_database_backend() {
+            // SQLite cannot add a constraint to an existing table
+            DatabaseBackend::Sqlite => rebuild_message_table(manager, Some(on_delete)).await,
+            _ => {
+                manager
+                .create_foreign_key(ForeignKey::create()
+                    .name(FK_NAME)
+                    .from(Message::Table, Message::UserId)
+                    .to(User::Table, User::Id)
+                    .on_delete(on_delete)
+                    .to_owned()
+                )
+                .await
+            }
+        }
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        match manager.get_database_backend() {
+            DatabaseBackend::Sqlite => rebuild_message_table(manager, None).await,
+            _ => {
+                manager
+                .drop_foreign_key(ForeignKey::drop()
+                    .name(FK_NAME)
+                    .table(Message::Table)
+                    .to_owned()
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Recreate the message table, with or without the foreign key, keeping its rows.
+async fn rebuild_message_table(manager: &SchemaManager<'_>, on_delete: Option<ForeignKeyAction>) -> Result<(), DbErr> {
+    let mut table = Table::create();
+    table
+        .table(MessageRebuild::Table)
+        .col(pk_auto(Message::Id))
+        .col(string(Message::Text))
+        .col(integer(Message::UserId))
+        .col(timestamp_with_time_zone_null(Message::CreatedAt))
+        .col(timestamp_with_time_zone_null(Message::UpdatedAt));
+    if let Some(on_delete) = on_delete {
+        table.foreign_key(ForeignKey::create()
+            .name(FK_NAME)
+            .from(MessageRebuild::Table, Message::UserId)
+            .to(User::Table, User::Id)
+            .on_delete(on_delete)
+        );
+    }
+    manager.create_table(table.to_owned()).await?;
+
+    let columns = [Message::Id, Message::Text, Message::UserId, Message::CreatedAt, Message::UpdatedAt];
+    manager
+    .exec_stmt(Query::insert()
+        .into_table(MessageRebuild::Table)
+        .columns(columns)
+        .select_from(Query::select().columns(columns).from(Message::Table).to_owned())
+        .map_err(|e| DbErr::Migration(e.to_string()))?
+        .to_owned()
+    )
+    .await?;
+
+    manager.drop_table(Table::drop().table(Message::Table).to_owned()).await?;
+    manager
+    .rename_table(Table::rename().table(MessageRebuild::Table, Message::Table).to_owned())
+    .await
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Message {
+    Table,
+    Id,
+    Text,
+    UserId,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum MessageRebuild {
+    #[sea_orm(iden = "message_rebuild")]
+    Table,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+    Name,
+    Email,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,53 @@
+use sea_orm_migration::{prelude::*, sea_orm::DatabaseBackend};
+
+/// Full-text index of `message.text` in an external content FTS5 table, kept up to date by triggers.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const SQLITE_UP: &[&str] = &[
+    "CREATE VIRTUAL TABLE message_fts USING fts5(text, content='message', content_rowid='id')",
+    "INSERT INTO message_fts(message_fts) VALUES ('rebuild')",
+    "CREATE TRIGGER message_fts_insert AFTER INSERT ON message BEGIN
+        INSERT INTO message_fts(rowid, text) VALUES (new.id, new.text);
+    END",
+    "CREATE TRIGGER message_fts_delete AFTER DELETE ON message BEGIN
+        INSERT INTO message_fts(message_fts, rowid, text) VALUES ('delete', old.id, old.text);
+    END",
+    "CREATE TRIGGER message_fts_update AFTER UPDATE OF text ON message BEGIN
+        INSERT INTO message_fts(message_fts, rowid, text) VALUES ('delete', old.id, old.text);
+        INSERT INTO message_fts(rowid, text) VALUES (new.id, new.text);
+    END",
+];
+
+const SQLITE_DOWN: &[&str] = &[
+    "DROP TRIGGER IF EXISTS message_fts_update",
+    "DROP TRIGGER IF EXISTS message_fts_delete",
+    "DROP TRIGGER IF EXISTS message_fts_insert",
+    "DROP TABLE IF EXISTS message_fts",
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        match manager.get_database_backend() {
+            DatabaseBackend::Sqlite => execute_all(manager, SQLITE_UP).await,
+            // Search is not available on other backends
+            _ => Ok(()),
+        }
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        match manager.get_database_backend() {
+            DatabaseBackend::Sqlite => execute_all(manager, SQLITE_DOWN).await,
+            _ => Ok(()),
+        }
+    }
+}
+
+async fn execute_all(manager: &SchemaManager<'_>, statements: &[&str]) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    for statement in statements {
+        db.execute_unprepared(statement).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+const INDEX_NAME: &str = "idx_message_client_msg_id";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Idempotency key of the producer (HTTP `Idempotency-Key` or Kafka `client_msg_id`)
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(Message::Table)
+            .add_column(ColumnDef::new(Message::ClientMsgId).string())
+            .to_owned()
+        )
+        .await?;
+
+        // Several rows may leave it NULL
+        manager
+        .create_index(Index::create()
+            .name(INDEX_NAME)
+            .table(Message::Table)
+            .col(Message::ClientMsgId)
+            .unique()
+            .to_owned()
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+        .drop_index(Index::drop().name(INDEX_NAME).table(Message::Table).to_owned())
+        .await?;
+
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(Message::Table)
+            .drop_column(Message::ClientMsgId)
+            .to_owned()
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    ClientMsgId,
+}
@@ -0,0 +1,78 @@
+use sea_orm_migration::{prelude::*, sea_orm::DatabaseBackend};
+
+const FK_NAME: &str = "fk_message_parent";
+const INDEX_NAME: &str = "idx_message_parent_id";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Replies of a deleted message become the start of their own thread
+        match manager.get_database_backend() {
+            // SQLite accepts a reference on a new column, as long as it defaults to NULL.
+            // Rebuilding the table instead would lose the full-text search triggers.
+            DatabaseBackend::Sqlite => {
+                manager
+                .get_connection()
+                .execute_unprepared("ALTER TABLE message ADD COLUMN parent_id integer NULL REFERENCES message(id) ON DELETE SET NULL")
+                .await?;
+            }
+            _ => {
+                manager
+                .alter_table(sea_query::Table::alter()
+                    .table(Message::Table)
+                    .add_column(ColumnDef::new(Message::ParentId).integer())
+                    .to_owned()
+                )
+                .await?;
+                manager
+                .create_foreign_key(ForeignKey::create()
+                    .name(FK_NAME)
+                    .from(Message::Table, Message::ParentId)
+                    .to(Message::Table, Message::Id)
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .to_owned()
+                )
+                .await?;
+            }
+        }
+
+        manager
+        .create_index(Index::create()
+            .name(INDEX_NAME)
+            .table(Message::Table)
+            .col(Message::ParentId)
+            .to_owned()
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+        .drop_index(Index::drop().name(INDEX_NAME).table(Message::Table).to_owned())
+        .await?;
+
+        if manager.get_database_backend() != DatabaseBackend::Sqlite {
+            manager
+            .drop_foreign_key(ForeignKey::drop().name(FK_NAME).table(Message::Table).to_owned())
+            .await?;
+        }
+
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(Message::Table)
+            .drop_column(Message::ParentId)
+            .to_owned()
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Id,
+    ParentId,
+}
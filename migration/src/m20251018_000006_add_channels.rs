@@ -0,0 +1,141 @@
+use sea_orm_migration::{prelude::*, schema::*, sea_orm::DatabaseBackend};
+
+const FK_NAME: &str = "fk_message_channel";
+const INDEX_NAME: &str = "idx_message_channel_id";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Channel::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Channel::Id))
+                    .col(string_uniq(Channel::Name))
+                    .col(timestamp_with_time_zone(Channel::CreatedAt))
+                    .col(timestamp_with_time_zone(Channel::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Memberships go away with the channel or the user
+        manager
+            .create_table(
+                Table::create()
+                    .table(ChannelMember::Table)
+                    .if_not_exists()
+                    .col(integer(ChannelMember::ChannelId))
+                    .col(integer(ChannelMember::UserId))
+                    .col(timestamp_with_time_zone(ChannelMember::CreatedAt))
+                    .primary_key(Index::create().col(ChannelMember::ChannelId).col(ChannelMember::UserId))
+                    .foreign_key(ForeignKey::create()
+                        .from(ChannelMember::Table, ChannelMember::ChannelId)
+                        .to(Channel::Table, Channel::Id)
+                        .on_delete(ForeignKeyAction::Cascade))
+                    .foreign_key(ForeignKey::create()
+                        .from(ChannelMember::Table, ChannelMember::UserId)
+                        .to(User::Table, User::Id)
+                        .on_delete(ForeignKeyAction::Cascade))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Messages without channel stay in the global stream. Those of a deleted channel go with it.
+        match manager.get_database_backend() {
+            // See m20251018_000005_add_message_parent_id
+            DatabaseBackend::Sqlite => {
+                manager
+                .get_connection()
+                .execute_unprepared("ALTER TABLE message ADD COLUMN channel_id integer NULL REFERENCES channel(id) ON DELETE CASCADE")
+                .await?;
+            }
+            _ => {
+                manager
+                .alter_table(sea_query::Table::alter()
+                    .table(Message::Table)
+                    .add_column(ColumnDef::new(Message::ChannelId).integer())
+                    .to_owned()
+                )
+                .await?;
+                manager
+                .create_foreign_key(ForeignKey::create()
+                    .name(FK_NAME)
+                    .from(Message::Table, Message::ChannelId)
+                    .to(Channel::Table, Channel::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .to_owned()
+                )
+                .await?;
+            }
+        }
+
+        manager
+        .create_index(Index::create()
+            .name(INDEX_NAME)
+            .table(Message::Table)
+            .col(Message::ChannelId)
+            .to_owned()
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+        .drop_index(Index::drop().name(INDEX_NAME).table(Message::Table).to_owned())
+        .await?;
+
+        if manager.get_database_backend() != DatabaseBackend::Sqlite {
+            manager
+            .drop_foreign_key(ForeignKey::drop().name(FK_NAME).table(Message::Table).to_owned())
+            .await?;
+        }
+
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(Message::Table)
+            .drop_column(Message::ChannelId)
+            .to_owned()
+        )
+        .await?;
+
+        manager
+            .drop_table(Table::drop().table(ChannelMember::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Channel::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ChannelMember {
+    Table,
+    ChannelId,
+    UserId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    ChannelId,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
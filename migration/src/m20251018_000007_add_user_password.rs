@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // PHC string of the password; users without one cannot log in
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(User::Table)
+            .add_column(ColumnDef::new(User::PasswordHash).string())
+            .to_owned()
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(User::Table)
+            .drop_column(User::PasswordHash)
+            .to_owned()
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    PasswordHash,
+}
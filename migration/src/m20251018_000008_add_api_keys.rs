@@ -0,0 +1,69 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+const INDEX_NAME: &str = "idx_api_key_user_id";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Only the SHA-256 of a key is stored, keys go away with their user
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiKey::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ApiKey::Id))
+                    .col(integer(ApiKey::UserId))
+                    .col(string(ApiKey::Name))
+                    .col(string(ApiKey::Prefix))
+                    .col(string_uniq(ApiKey::KeyHash))
+                    .col(json(ApiKey::Scopes))
+                    .col(timestamp_with_time_zone_null(ApiKey::ExpiresAt))
+                    .col(timestamp_with_time_zone_null(ApiKey::LastUsedAt))
+                    .col(timestamp_with_time_zone(ApiKey::CreatedAt))
+                    .foreign_key(ForeignKey::create()
+                        .from(ApiKey::Table, ApiKey::UserId)
+                        .to(User::Table, User::Id)
+                        .on_delete(ForeignKeyAction::Cascade))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+        .create_index(Index::create()
+            .name(INDEX_NAME)
+            .table(ApiKey::Table)
+            .col(ApiKey::UserId)
+            .to_owned()
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiKey::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ApiKey {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Prefix,
+    KeyHash,
+    Scopes,
+    ExpiresAt,
+    LastUsedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
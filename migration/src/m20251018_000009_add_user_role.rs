@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Existing users become members, admins are promoted with `wamserver role`
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(User::Table)
+            .add_column(ColumnDef::new(User::Role).string_len(16).not_null().default("member"))
+            .to_owned()
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(User::Table)
+            .drop_column(User::Role)
+            .to_owned()
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Role,
+}
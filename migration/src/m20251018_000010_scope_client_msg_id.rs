@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+const OLD_INDEX_NAME: &str = "idx_message_client_msg_id";
+const INDEX_NAME: &str = "idx_message_user_client_msg_id";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Idempotency keys belong to their producer, two users may pick the same one
+        manager
+        .create_index(Index::create()
+            .name(INDEX_NAME)
+            .table(Message::Table)
+            .col(Message::UserId)
+            .col(Message::ClientMsgId)
+            .unique()
+            .to_owned()
+        )
+        .await?;
+
+        manager
+        .drop_index(Index::drop().name(OLD_INDEX_NAME).table(Message::Table).to_owned())
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Fails, leaving the schema untouched, once two users share a key
+        manager
+        .create_index(Index::create()
+            .name(OLD_INDEX_NAME)
+            .table(Message::Table)
+            .col(Message::ClientMsgId)
+            .unique()
+            .to_owned()
+        )
+        .await?;
+
+        manager
+        .drop_index(Index::drop().name(INDEX_NAME).table(Message::Table).to_owned())
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    UserId,
+    ClientMsgId,
+}
@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The source of existing messages is unknown, they are left out of the ingestion counts
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(Message::Table)
+            .add_column(ColumnDef::new(Message::Source).string_len(16).null())
+            .to_owned()
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(Message::Table)
+            .drop_column(Message::Source)
+            .to_owned()
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Source,
+}
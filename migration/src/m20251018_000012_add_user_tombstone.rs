@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+/// Must match `TOMBSTONE_EMAIL` of the server, only used to find an existing tombstone
+const TOMBSTONE_EMAIL: &str = "deleted-user@wam.invalid";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The email of the tombstone user can be edited, the flag cannot
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(User::Table)
+            .add_column(ColumnDef::new(User::Tombstone).boolean().not_null().default(false))
+            .to_owned()
+        )
+        .await?;
+        manager
+        .exec_stmt(Query::update()
+            .table(User::Table)
+            .value(User::Tombstone, true)
+            .and_where(Expr::col(User::Email).eq(TOMBSTONE_EMAIL))
+            .to_owned()
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(User::Table)
+            .drop_column(User::Tombstone)
+            .to_owned()
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Email,
+    Tombstone,
+}
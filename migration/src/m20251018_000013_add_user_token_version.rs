@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Tokens carry the version they were issued under, bumping it revokes them all
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(User::Table)
+            .add_column(ColumnDef::new(User::TokenVersion).integer().not_null().default(0))
+            .to_owned()
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+        .alter_table(sea_query::Table::alter()
+            .table(User::Table)
+            .drop_column(User::TokenVersion)
+            .to_owned()
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    TokenVersion,
+}
@@ -0,0 +1,165 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{
+        sqlx::{self, Connection, SqliteConnection},
+        DatabaseBackend,
+    },
+    SchemaManagerConnection,
+};
+
+/// Columns of the tables as left by the previous migrations, `{timestamp}` is the nullability of both timestamps
+const USER_COLUMNS: &str = r#""id" integer NOT NULL PRIMARY KEY AUTOINCREMENT, "name" varchar NOT NULL,
+    "email" varchar NOT NULL UNIQUE, "created_at" timestamp_with_timezone_text {timestamp},
+    "updated_at" timestamp_with_timezone_text {timestamp}, "password_hash" varchar NULL,
+    "role" varchar(16) NOT NULL DEFAULT 'member', "tombstone" boolean NOT NULL DEFAULT FALSE,
+    "token_version" integer NOT NULL DEFAULT 0"#;
+const MESSAGE_COLUMNS: &str = r#""id" integer NOT NULL PRIMARY KEY AUTOINCREMENT, "text" varchar NOT NULL,
+    "user_id" integer NOT NULL, "created_at" timestamp_with_timezone_text {timestamp},
+    "updated_at" timestamp_with_timezone_text {timestamp}, "client_msg_id" varchar NULL,
+    "parent_id" integer NULL REFERENCES "message" ("id") ON DELETE SET NULL,
+    "channel_id" integer NULL REFERENCES "channel" ("id") ON DELETE CASCADE, "source" varchar(16) NULL,
+    FOREIGN KEY ("user_id") REFERENCES "user" ("id") ON DELETE RESTRICT"#;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Rows written before the server always set both timestamps get whichever one they have
+        let now = chrono::Utc::now();
+        for table in [Message::Table.into_iden(), User::Table.into_iden()] {
+            manager
+            .exec_stmt(Query::update()
+                .table(table)
+                .value(Timestamps::CreatedAt, Expr::col(Timestamps::CreatedAt).if_null(Expr::col(Timestamps::UpdatedAt).if_null(now)))
+                .value(Timestamps::UpdatedAt, Expr::col(Timestamps::UpdatedAt).if_null(Expr::col(Timestamps::CreatedAt).if_null(now)))
+                .cond_where(Cond::any()
+                    .add(Expr::col(Timestamps::CreatedAt).is_null())
+                    .add(Expr::col(Timestamps::UpdatedAt).is_null())
+                )
+                .to_owned()
+            )
+            .await?;
+        }
+
+        set_timestamps_null(manager, false).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        set_timestamps_null(manager, true).await
+    }
+}
+
+async fn set_timestamps_null(manager: &SchemaManager<'_>, null: bool) -> Result<(), DbErr> {
+    if manager.get_database_backend() == DatabaseBackend::Sqlite {
+        // SQLite cannot change a column, both tables are rebuilt
+        return rebuild_tables(manager, if null { "NULL" } else { "NOT NULL" }).await;
+    }
+
+    for table in [Message::Table.into_iden(), User::Table.into_iden()] {
+        for column in [Timestamps::CreatedAt, Timestamps::UpdatedAt] {
+            let mut column = ColumnDef::new(column);
+            column.timestamp_with_time_zone();
+            if null { column.null() } else { column.not_null() };
+            manager
+            .alter_table(sea_query::Table::alter()
+                .table(table.clone())
+                .modify_column(column)
+                .to_owned()
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Rebuild `user` and `message` the way SQLite documents it: dropping `user` with foreign keys on would
+/// delete the rows referencing it, so they are turned off on one connection and the rebuild runs in a
+/// transaction on it, checked before commit.
+async fn rebuild_tables(manager: &SchemaManager<'_>, timestamp: &str) -> Result<(), DbErr> {
+    let SchemaManagerConnection::Connection(db) = manager.get_connection() else {
+        return Err(DbErr::Migration("SQLite ignores turning foreign keys off in a transaction".to_string()));
+    };
+    let mut conn = db.get_sqlite_connection_pool().acquire().await.map_err(sqlx_err)?;
+
+    sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut *conn).await.map_err(sqlx_err)?;
+    let result = async {
+        let mut tx = conn.begin().await.map_err(sqlx_err)?;
+        rebuild_table(&mut tx, "user", &USER_COLUMNS.replace("{timestamp}", timestamp)).await?;
+        rebuild_table(&mut tx, "message", &MESSAGE_COLUMNS.replace("{timestamp}", timestamp)).await?;
+        let violations = sqlx::query("PRAGMA foreign_key_check").fetch_all(&mut *tx).await.map_err(sqlx_err)?;
+        if !violations.is_empty() {
+            return Err(DbErr::Migration(format!("{} rows would break a foreign key", violations.len())));
+        }
+        tx.commit().await.map_err(sqlx_err)
+    }
+    .await;
+    sqlx::query("PRAGMA foreign_keys = ON").execute(&mut *conn).await.map_err(sqlx_err)?;
+    result
+}
+
+/// Replace `table` by a new one with `columns`, keeping its rows, indexes, triggers and id sequence.
+async fn rebuild_table(conn: &mut SqliteConnection, table: &str, columns: &str) -> Result<(), DbErr> {
+    let rebuild = format!("{}_rebuild", table);
+    let schema: Vec<String> = sqlx::query_scalar(
+        "SELECT sql FROM sqlite_master WHERE tbl_name = ? AND type IN ('index', 'trigger') AND sql IS NOT NULL"
+    )
+    .bind(table)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(sqlx_err)?;
+    let names: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+        .bind(table)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(sqlx_err)?;
+    let names = names.iter().map(|name| format!("\"{}\"", name)).collect::<Vec<_>>().join(", ");
+    let sequence: Option<i64> = sqlx::query_scalar("SELECT seq FROM sqlite_sequence WHERE name = ?")
+        .bind(table)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(sqlx_err)?;
+
+    let mut statements = vec![
+        format!("CREATE TABLE \"{}\" ({})", rebuild, columns),
+        format!("INSERT INTO \"{}\" ({}) SELECT {} FROM \"{}\"", rebuild, names, names, table),
+        format!("DROP TABLE \"{}\"", table),
+        format!("ALTER TABLE \"{}\" RENAME TO \"{}\"", rebuild, table),
+    ];
+    statements.extend(schema);
+    for statement in statements {
+        sqlx::query(&statement).execute(&mut *conn).await.map_err(sqlx_err)?;
+    }
+
+    // Ids of deleted rows at the end of the table are never handed out again
+    if let Some(sequence) = sequence {
+        sqlx::query("UPDATE sqlite_sequence SET seq = MAX(seq, ?) WHERE name = ?")
+            .bind(sequence)
+            .bind(table)
+            .execute(&mut *conn)
+            .await
+            .map_err(sqlx_err)?;
+    }
+    Ok(())
+}
+
+fn sqlx_err(e: sqlx::Error) -> DbErr {
+    DbErr::Migration(e.to_string())
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+}
+
+#[derive(DeriveIden)]
+enum Timestamps {
+    CreatedAt,
+    UpdatedAt,
+}
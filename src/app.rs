@@ -0,0 +1,135 @@
+use axum::{
+    routing::get,
+    routing::post,
+    routing::put,
+    routing::delete,
+    routing::any,
+    Router,
+    response::IntoResponse,
+    http::Method,
+    middleware,
+};
+use tower_http::{
+    services::ServeDir,
+    cors::CorsLayer,
+};
+
+use crate::config::ListenerRoutes;
+use crate::{routes, WamServerState};
+
+/// The public `/api` routes (messages, users, WebSocket...), ready to be nested anywhere.
+pub fn build_api_router(state: WamServerState) -> Router {
+    // Message writes, closed to anonymous and read-only users
+    let write_routes = Router::new()
+        .route("/message", post(routes::services::create_message))
+        .route("/message/batch", post(routes::services::create_messages_batch))
+        .route("/message/{id}", put(routes::services::update_message)
+            .patch(routes::services::patch_message)
+            .delete(routes::services::delete_message))
+        .route_layer(middleware::from_fn(routes::auth::require_writer));
+
+    // Admin controls
+    let admin_routes = Router::new()
+        .route("/user", post(routes::services::create_user))
+        .route("/user/{id}", delete(routes::services::delete_user))
+        .route("/user/{id}/role", put(routes::services::set_user_role))
+        .route("/channel", post(routes::channels::create_channel))
+        .route("/channel/{id}/members", post(routes::channels::add_channel_member))
+        .route("/channel/{id}/members/{user_id}", delete(routes::channels::remove_channel_member))
+        .route_layer(middleware::from_fn(routes::auth::require_admin));
+
+    Router::new()
+        .route("/ws", any(routes::socket::ws_handler))
+        .route("/message", get(routes::services::get_messages))
+        .route("/message/search", get(routes::services::search_messages))
+        .route("/message/{id}", get(routes::services::get_message))
+        .route("/message/{id}/thread", get(routes::services::get_thread))
+        .route("/auth/login", post(routes::auth::login))
+        .route("/auth/refresh", post(routes::auth::refresh))
+        .route("/auth/logout", post(routes::auth::logout))
+        .route("/auth/keys", get(routes::auth::get_api_keys).post(routes::auth::create_api_key))
+        .route("/auth/keys/{id}", delete(routes::auth::revoke_api_key))
+        .route("/info", get(routes::services::get_info))
+        .route("/parameters", get(routes::parameters::get_kafka_parameters))
+        .route("/user", get(routes::services::get_users))
+        .route("/user/{id}", get(routes::services::get_user)
+            .put(routes::services::update_user)
+            .patch(routes::services::patch_user))
+        .route("/channel", get(routes::channels::get_channels))
+        .route("/channel/{id}/members", get(routes::channels::get_channel_members))
+        .merge(write_routes)
+        .merge(admin_routes)
+        // Outermost, so that the guards see the authenticated user
+        .route_layer(middleware::from_fn_with_state(state.clone(), routes::auth::authenticate))
+        .with_state(state)
+}
+
+/// Operational routes (metrics, health checks, vehicle injection), meant for internal listeners.
+pub fn build_admin_router(state: WamServerState) -> Router {
+    let admin_router = Router::new()
+        .route("/metrics", get(routes::metrics::metrics))
+        .route("/api/health", get(routes::health::health))
+        .route("/api/ready", get(routes::health::ready));
+
+    // Injected vehicles reach every client, only admins may push them
+    #[cfg(feature = "sytral")]
+    let admin_router = admin_router.merge(Router::new()
+        .route("/api/vehicles", post(routes::services::publish_vehicles))
+        .route_layer(middleware::from_fn(routes::auth::require_admin))
+        .route_layer(middleware::from_fn_with_state(state.clone(), routes::auth::authenticate)));
+
+    admin_router.with_state(state)
+}
+
+/// The whole wamserver application: API, admin routes, SPA routes and static files.
+pub fn build_app(state: WamServerState) -> Router {
+    build_listener_app(state, ListenerRoutes::All)
+}
+
+/// The application served by a listener, restricted to its `routes`.
+pub fn build_listener_app(state: WamServerState, routes: ListenerRoutes) -> Router {
+    match routes {
+        ListenerRoutes::Admin => build_admin_router(state),
+        ListenerRoutes::Public => build_public_app(state),
+        ListenerRoutes::All => build_public_app(state.clone()).merge(build_admin_router(state)),
+    }
+}
+
+fn build_public_app(state: WamServerState) -> Router {
+
+    // Create static file service with proper MIME types
+    async fn serve_index() -> impl IntoResponse {
+        let index_path = std::path::Path::new("static/index.html");
+        match tokio::fs::read_to_string(index_path).await {
+            Ok(content) => axum::response::Html(content).into_response(),
+            Err(_) => (
+                axum::http::StatusCode::NOT_FOUND,
+                "Not Found"
+            ).into_response(),
+        }
+    }
+
+    // Handle SPA routes
+    let spa_routes = Router::new()
+        .route("/front/{*page}", get(serve_index));
+
+    // Serve static files
+    let static_service = ServeDir::new("static")
+        .append_index_html_on_directories(true);
+
+    // Configure CORS for Docker environment
+    let cors = CorsLayer::new()
+        // Allow all origins since we're in a Docker environment
+        .allow_origin(tower_http::cors::Any)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS, Method::HEAD, Method::PATCH])
+        .allow_headers(tower_http::cors::Any)
+        .expose_headers(tower_http::cors::Any)
+        .max_age(std::time::Duration::from_secs(3600));
+
+    Router::new()
+        .route("/about", get(routes::pages::about))
+        .nest("/api", build_api_router(state))
+        .merge(spa_routes)
+        .layer(cors)
+        .fallback_service(static_service)
+}
@@ -0,0 +1,210 @@
+use std::sync::LazyLock;
+
+use argon2::Argon2;
+use password_hash::rand_core::{OsRng, RngCore};
+use password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use chrono::Utc;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use entity::user;
+
+use crate::config::AuthConfig;
+use crate::error::WamError;
+
+/// What a JWT may be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    /// Authenticates requests, short lived
+    Access,
+    /// Only exchanged for a new pair at `/api/auth/refresh`
+    Refresh,
+}
+
+/// Claims of the tokens issued by the server
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    /// Id of the user
+    pub sub: String,
+    pub kind: TokenKind,
+    /// `token_version` of the user at issue time, tokens of an older version are revoked
+    pub ver: i32,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// What a valid token says about its holder
+#[derive(Debug, Clone, Copy)]
+pub struct VerifiedToken {
+    pub user_id: i32,
+    pub version: i32,
+    /// Unix timestamp
+    pub expires_at: i64,
+}
+
+impl VerifiedToken {
+    /// Fail when the token was issued before the user's tokens were last revoked.
+    pub fn check_version(&self, user: &user::Model) -> Result<(), WamError> {
+        if self.version != user.token_version {
+            return Err(WamError::Unauthorized("this token has been revoked".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Body of the login and refresh responses
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: &'static str,
+    /// Lifetime of the access token, in seconds
+    pub expires_in: u64,
+}
+
+/// Signing keys and lifetimes of the session tokens.
+pub struct AuthKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    access_ttl_secs: u64,
+    refresh_ttl_secs: u64,
+}
+
+impl AuthKeys {
+    /// Without `auth.jwt_secret` a random secret is drawn, tokens then die with the process.
+    pub fn new(config: &AuthConfig) -> Self {
+        let secret = if config.jwt_secret.is_empty() {
+            warn!("auth.jwt_secret is not set, sessions will not survive a restart");
+            let mut secret = vec![0u8; 32];
+            OsRng.fill_bytes(&mut secret);
+            secret
+        } else {
+            config.jwt_secret.as_bytes().to_vec()
+        };
+
+        AuthKeys {
+            encoding: EncodingKey::from_secret(&secret),
+            decoding: DecodingKey::from_secret(&secret),
+            access_ttl_secs: config.access_token_ttl_secs,
+            refresh_ttl_secs: config.refresh_token_ttl_secs,
+        }
+    }
+
+    /// A new access and refresh token pair for the user.
+    pub fn issue(&self, user: &user::Model) -> Result<TokenPair, WamError> {
+        Ok(TokenPair {
+            access_token: self.sign(user, TokenKind::Access, self.access_ttl_secs)?,
+            refresh_token: self.sign(user, TokenKind::Refresh, self.refresh_ttl_secs)?,
+            token_type: "Bearer",
+            expires_in: self.access_ttl_secs,
+        })
+    }
+
+    fn sign(&self, user: &user::Model, kind: TokenKind, ttl_secs: u64) -> Result<String, WamError> {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            sub: user.id.to_string(),
+            kind,
+            ver: user.token_version,
+            iat: now,
+            exp: now + ttl_secs as i64,
+        };
+        jsonwebtoken::encode(&Header::default(), &claims, &self.encoding)
+            .map_err(|e| WamError::Internal(format!("cannot sign token: {}", e)))
+    }
+
+    /// The user a valid, unexpired token of this kind was issued to. Whether it was revoked since
+    /// is left to `VerifiedToken::check_version`, once the user is loaded.
+    pub fn verify(&self, token: &str, kind: TokenKind) -> Result<VerifiedToken, WamError> {
+        let claims = jsonwebtoken::decode::<Claims>(token, &self.decoding, &Validation::default())
+            .map_err(|e| WamError::Unauthorized(format!("invalid token: {}", e)))?
+            .claims;
+        if claims.kind != kind {
+            return Err(WamError::Unauthorized(match kind {
+                TokenKind::Access => "expected an access token".to_string(),
+                TokenKind::Refresh => "expected a refresh token".to_string(),
+            }));
+        }
+        let user_id = claims.sub.parse()
+            .map_err(|_| WamError::Unauthorized("invalid token subject".to_string()))?;
+        Ok(VerifiedToken { user_id, version: claims.ver, expires_at: claims.exp })
+    }
+}
+
+/// Leading characters of every API key, also how a bearer key is told apart from a JWT.
+pub const API_KEY_PREFIX: &str = "wam_";
+
+/// Characters of a key kept in clear, to tell keys apart in listings
+const API_KEY_DISPLAY_LEN: usize = 12;
+
+/// A freshly drawn API key, only shown to its owner once.
+pub struct NewApiKey {
+    pub key: String,
+    pub prefix: String,
+    pub hash: String,
+}
+
+impl NewApiKey {
+    pub fn generate() -> Self {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        let key = format!("{}{}", API_KEY_PREFIX, to_hex(&secret));
+        NewApiKey {
+            prefix: key[..API_KEY_DISPLAY_LEN].to_string(),
+            hash: hash_api_key(&key),
+            key,
+        }
+    }
+}
+
+/// SHA-256 of an API key. Keys are random enough that a slow hash would only slow down every request.
+pub fn hash_api_key(key: &str) -> String {
+    to_hex(&Sha256::digest(key.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Argon2 PHC string of the password. Hashing is slow on purpose, so it runs off the async threads.
+pub async fn hash_password(password: String) -> Result<String, WamError> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| WamError::Internal(format!("cannot hash password: {}", e)))
+    })
+    .await
+    .map_err(|e| WamError::Internal(format!("password hashing failed: {}", e)))?
+}
+
+/// Hash checked when there is no user or no password to check, so that logins take as long either way
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(b"no password to check", &salt)
+        .map(|hash| hash.to_string())
+        .unwrap_or_default()
+});
+
+/// Spend the time of a password check, always failing.
+pub async fn reject_password(password: String) -> bool {
+    let _ = tokio::task::spawn_blocking(move || verify_hash(&password, &DUMMY_PASSWORD_HASH)).await;
+    false
+}
+
+/// Whether the password matches the PHC string, a malformed hash never matches.
+pub async fn verify_password(password: String, hash: String) -> bool {
+    tokio::task::spawn_blocking(move || verify_hash(&password, &hash))
+        .await
+        .unwrap_or(false)
+}
+
+fn verify_hash(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .is_ok_and(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+}
@@ -0,0 +1,178 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use entity::user::Role;
+
+use crate::config::WamConfig;
+
+#[derive(Debug, Parser)]
+#[command(name = "wamserver", version, about = "WAM message and vehicle server")]
+pub struct Cli {
+    /// Defaults to `serve` when omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the HTTP/WebSocket server with its background tasks
+    Serve(ServeArgs),
+    /// Manage the database schema
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
+    /// Insert demo users and messages
+    Seed(SeedArgs),
+    /// Dump users and messages as JSON
+    Export(ExportArgs),
+    /// Feed a recorded SYTRAL or Kafka capture into a running instance
+    Replay(ReplayArgs),
+    /// Change the role of a user, creating them if needed, e.g. to promote the first admin
+    Role(RoleArgs),
+}
+
+#[derive(Debug, Default, Args)]
+pub struct ServeArgs {
+    /// Do not start the Kafka consumer
+    #[arg(long)]
+    pub no_kafka: bool,
+    /// Do not start the SYTRAL poller
+    #[arg(long)]
+    pub no_sytral: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateAction {
+    /// Apply pending migrations
+    Up {
+        /// Number of migrations to apply (all pending when omitted)
+        #[arg(long)]
+        steps: Option<u32>,
+    },
+    /// Roll back applied migrations
+    Down {
+        #[arg(long, default_value_t = 1)]
+        steps: u32,
+    },
+    /// List migrations and whether they are applied
+    Status,
+}
+
+#[derive(Debug, Args)]
+pub struct SeedArgs {
+    /// Number of messages of each demo user, those already seeded are kept
+    #[arg(long, default_value_t = 5)]
+    pub messages_per_user: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Export users (both tables are exported when neither flag is given)
+    #[arg(long)]
+    pub users: bool,
+    /// Export messages
+    #[arg(long)]
+    pub messages: bool,
+    #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+    pub format: ExportFormat,
+    /// Output file, standard output when omitted
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// A single JSON document
+    Json,
+    /// One row per line, for a single table
+    Ndjson,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    /// Kind of capture contained in the file
+    #[arg(value_enum)]
+    pub kind: CaptureKind,
+    /// Capture file, one record per line
+    pub file: PathBuf,
+    /// Base URL of the running instance, for SYTRAL captures
+    #[arg(long, default_value = "http://localhost:3000")]
+    pub target: String,
+    /// Delay between two records
+    #[arg(long, default_value_t = 0)]
+    pub interval_ms: u64,
+    /// Kafka broker to produce Kafka captures to
+    #[arg(long, env = "KAFKA_URL")]
+    pub kafka_url: Option<String>,
+    /// Topic to produce Kafka captures to, a channel topic posts them in its channel
+    #[arg(long, env = "KAFKA_TOPIC")]
+    pub topic: Option<String>,
+    /// API key sent as `X-Api-Key` with SYTRAL captures, of an admin with the `admin` scope
+    #[arg(long, env = "WAM_API_KEY", hide_env_values = true, conflicts_with = "token")]
+    pub api_key: Option<String>,
+    /// Access token sent as `Authorization: Bearer`, instead of an API key
+    #[arg(long, env = "WAM_TOKEN", hide_env_values = true)]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CaptureKind {
+    /// Kafka message payloads, produced to a Kafka topic for the consumer of the instance
+    Kafka,
+    /// Vehicle lists or raw SIRI-Lite responses, replayed through `POST /api/vehicles`
+    Sytral,
+}
+
+#[derive(Debug, Args)]
+pub struct RoleArgs {
+    /// Email of the user
+    pub email: String,
+    #[arg(value_enum)]
+    pub role: RoleArg,
+    /// Also set the password of the user, so that a new admin can log in; required when the user is created
+    #[arg(long, env = "WAM_USER_PASSWORD", hide_env_values = true)]
+    pub password: Option<String>,
+    /// Name of the user, created when no user has this email (defaults to the part of the email before `@`)
+    #[arg(long)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RoleArg {
+    Admin,
+    Member,
+    #[value(name = "read_only")]
+    ReadOnly,
+}
+
+impl From<RoleArg> for Role {
+    fn from(role: RoleArg) -> Self {
+        match role {
+            RoleArg::Admin => Role::Admin,
+            RoleArg::Member => Role::Member,
+            RoleArg::ReadOnly => Role::ReadOnly,
+        }
+    }
+}
+
+impl Command {
+    /// Adjust the configuration to what the command actually needs.
+    pub fn configure(&self, config: &mut WamConfig) {
+        match self {
+            Command::Serve(args) => {
+                if args.no_kafka {
+                    config.kafka.enabled = false;
+                }
+                if args.no_sytral {
+                    config.sytral.enabled = false;
+                }
+            }
+            // Ops commands only talk to the database
+            _ => {
+                config.kafka.enabled = false;
+                config.sytral.enabled = false;
+            }
+        }
+    }
+}
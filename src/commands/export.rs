@@ -0,0 +1,55 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use anyhow::bail;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::cli::{ExportArgs, ExportFormat};
+use crate::config::WamConfig;
+use crate::database::requests::{MessageFilter, UserFilter};
+use crate::database::WamDatabase;
+
+pub async fn run(config: &WamConfig, args: &ExportArgs) -> anyhow::Result<()> {
+    // Neither flag means everything
+    let (users, messages) = match (args.users, args.messages) {
+        (false, false) => (true, true),
+        selected => selected,
+    };
+    if args.format == ExportFormat::Ndjson && users && messages {
+        bail!("ndjson export needs a single table: pass --users or --messages");
+    }
+
+    let db = WamDatabase::open(&config.database).await?;
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    match args.format {
+        ExportFormat::Json => {
+            let mut document = Map::new();
+            if users {
+                document.insert("users".to_string(), serde_json::to_value(db.get_users(&UserFilter::default()).await?.users)?);
+            }
+            if messages {
+                document.insert("messages".to_string(), serde_json::to_value(db.get_messages(&MessageFilter::default()).await?.messages)?);
+            }
+            serde_json::to_writer_pretty(&mut out, &Value::Object(document))?;
+            writeln!(out)?;
+        }
+        ExportFormat::Ndjson if users => write_lines(&mut out, &db.get_users(&UserFilter::default()).await?.users)?,
+        ExportFormat::Ndjson => write_lines(&mut out, &db.get_messages(&MessageFilter::default()).await?.messages)?,
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+fn write_lines<T: Serialize>(out: &mut dyn Write, rows: &[T]) -> anyhow::Result<()> {
+    for row in rows {
+        serde_json::to_writer(&mut *out, row)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use migration::{Migrator, MigratorTrait};
+
+use crate::cli::MigrateAction;
+use crate::config::WamConfig;
+use crate::database::WamDatabase;
+
+pub async fn run(config: &WamConfig, action: MigrateAction) -> anyhow::Result<()> {
+    let db = WamDatabase::connect(&config.database).await?;
+
+    match action {
+        MigrateAction::Up { steps } => Migrator::up(&db.conn, steps).await?,
+        MigrateAction::Down { steps } => Migrator::down(&db.conn, Some(steps)).await?,
+        MigrateAction::Status => {
+            for migration in Migrator::get_migration_with_status(&db.conn).await? {
+                println!("{:<8} {}", migration.status().to_string(), migration.name());
+            }
+        }
+    }
+
+    Ok(())
+}
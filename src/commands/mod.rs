@@ -0,0 +1,29 @@
+use crate::cli::Command;
+use crate::config::WamConfig;
+
+pub mod export;
+pub mod migrate;
+pub mod replay;
+pub mod role;
+pub mod seed;
+pub mod serve;
+
+/// Load the configuration needed by the command, then run it.
+pub async fn run(command: Command) -> anyhow::Result<()> {
+    // Replaying only talks to Kafka or a running instance
+    if let Command::Replay(args) = &command {
+        return replay::run(args).await;
+    }
+
+    // Load and check the whole configuration before starting anything
+    let config = WamConfig::load_with(|config| command.configure(config))?;
+
+    match command {
+        Command::Serve(_) => serve::run(config).await,
+        Command::Migrate { action } => migrate::run(&config, action).await,
+        Command::Seed(args) => seed::run(&config, &args).await,
+        Command::Export(args) => export::run(&config, &args).await,
+        Command::Role(args) => role::run(&config, &args).await,
+        Command::Replay(_) => unreachable!("handled above"),
+    }
+}
@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use log::{error, info};
+use reqwest::{Client, RequestBuilder};
+use serde_json::Value;
+
+use crate::cli::{CaptureKind, ReplayArgs};
+#[cfg(feature = "sytral")]
+use crate::messaging::sytral;
+
+/// Send every record of the capture, in order: Kafka payloads back to a topic, vehicles to the running instance.
+pub async fn run(args: &ReplayArgs) -> anyhow::Result<()> {
+    let content = tokio::fs::read_to_string(&args.file)
+        .await
+        .with_context(|| format!("cannot read capture {}", args.file.display()))?;
+
+    match args.kind {
+        CaptureKind::Kafka => produce(args, &content).await,
+        CaptureKind::Sytral => post(args, &content).await,
+    }
+}
+
+/// Kafka payloads go through the consumer of the instance, which keeps their author, source and channel topic.
+#[cfg(feature = "kafka")]
+async fn produce(args: &ReplayArgs, content: &str) -> anyhow::Result<()> {
+    use kafka::producer::{Producer, Record, RequiredAcks};
+
+    let (Some(url), Some(topic)) = (&args.kafka_url, &args.topic) else {
+        bail!("Kafka captures are produced to a topic, pass --kafka-url and --topic");
+    };
+    let mut producer = Producer::from_hosts(vec![url.to_owned()])
+        .with_ack_timeout(Duration::from_secs(5))
+        .with_required_acks(RequiredAcks::One)
+        .create()
+        .with_context(|| format!("cannot connect to Kafka at {}", url))?;
+    let mut sent = 0;
+
+    for (index, line) in records(content) {
+        serde_json::from_str::<Value>(line)
+            .with_context(|| format!("invalid record on line {}", index + 1))?;
+        producer
+            .send(&Record::from_value(topic, line.as_bytes()))
+            .with_context(|| format!("cannot produce line {} to {}", index + 1, topic))?;
+        sent += 1;
+        pause(args).await;
+    }
+
+    info!("Replayed {} record(s) to Kafka topic {}", sent, topic);
+    Ok(())
+}
+
+#[cfg(not(feature = "kafka"))]
+async fn produce(_args: &ReplayArgs, _content: &str) -> anyhow::Result<()> {
+    bail!("Kafka support is not compiled in")
+}
+
+async fn post(args: &ReplayArgs, content: &str) -> anyhow::Result<()> {
+    let url = format!("{}/api/vehicles", args.target.trim_end_matches('/'));
+    if args.api_key.is_none() && args.token.is_none() {
+        bail!("the server rejects anonymous writes, pass --api-key or --token");
+    }
+    let client = Client::new();
+    let mut sent = 0;
+    let mut failed = 0;
+
+    for (index, line) in records(content) {
+        let body = parse_vehicles(line)
+            .with_context(|| format!("invalid record on line {}", index + 1))?;
+
+        match authorize(client.post(&url), args).json(&body).send().await {
+            Ok(response) if response.status().is_success() => sent += 1,
+            Ok(response) => {
+                error!("Line {} rejected by {}: {}", index + 1, url, response.status());
+                failed += 1;
+            }
+            Err(e) => bail!("cannot reach {}: {}", url, e),
+        }
+        pause(args).await;
+    }
+
+    info!("Replayed {} record(s) to {}, {} rejected", sent, url, failed);
+    Ok(())
+}
+
+fn records(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content.lines().enumerate().filter(|(_, l)| !l.trim().is_empty())
+}
+
+async fn pause(args: &ReplayArgs) {
+    if args.interval_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(args.interval_ms)).await;
+    }
+}
+
+fn authorize(request: RequestBuilder, args: &ReplayArgs) -> RequestBuilder {
+    match (&args.api_key, &args.token) {
+        (Some(api_key), _) => request.header("X-Api-Key", api_key),
+        (None, Some(token)) => request.bearer_auth(token),
+        (None, None) => request,
+    }
+}
+
+#[cfg(feature = "sytral")]
+fn parse_vehicles(line: &str) -> anyhow::Result<Value> {
+    Ok(serde_json::to_value(sytral::parse_capture(line)?)?)
+}
+
+#[cfg(not(feature = "sytral"))]
+fn parse_vehicles(_line: &str) -> anyhow::Result<Value> {
+    bail!("SYTRAL support is not compiled in")
+}
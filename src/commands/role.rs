@@ -0,0 +1,59 @@
+use anyhow::{bail, Context};
+use entity::user::Role;
+use entity::validation::{MAX_PASSWORD_LEN, MIN_PASSWORD_LEN};
+use log::info;
+use validator::Validate;
+
+use crate::auth::hash_password;
+use crate::cli::RoleArgs;
+use crate::config::WamConfig;
+use crate::database::WamDatabase;
+
+/// Change the role of a user, creating them when no user has the email, the only way to get a first admin.
+pub async fn run(config: &WamConfig, args: &RoleArgs) -> anyhow::Result<()> {
+    let db = WamDatabase::open(&config.database).await?;
+    let role: Role = args.role.into();
+
+    let password_hash = match &args.password {
+        Some(password) => {
+            let len = password.chars().count() as u64;
+            if !(MIN_PASSWORD_LEN..=MAX_PASSWORD_LEN).contains(&len) {
+                bail!("the password must be {} to {} characters long", MIN_PASSWORD_LEN, MAX_PASSWORD_LEN);
+            }
+            Some(hash_password(password.clone()).await?)
+        }
+        None => None,
+    };
+
+    let Some(user) = db.get_user_by_email(&args.email).await? else {
+        let password_hash = password_hash
+            .with_context(|| format!("no user with email {}, give a password with --password or WAM_USER_PASSWORD to create them", args.email))?;
+        let name = args.name.clone()
+            .unwrap_or_else(|| args.email.split('@').next().unwrap_or_default().to_string());
+        let user = entity::user::Model {
+            id: 0,
+            name,
+            email: args.email.clone(),
+            password_hash: Some(password_hash),
+            role,
+            tombstone: false,
+            token_version: 0,
+            created_at: Default::default(),
+            updated_at: Default::default(),
+        };
+        user.validate()?;
+        let user = db.create_user(user).await?;
+        info!("User {} created as {:?}", user.email, user.role);
+        return Ok(());
+    };
+
+    if user.role == Role::Admin && role != Role::Admin && db.count_users_with_role(Role::Admin).await? <= 1 {
+        bail!("{} is the last admin, promote another user first", user.email);
+    }
+    if let Some(password_hash) = password_hash {
+        db.update_user(user.id, None, None, Some(password_hash)).await?;
+    }
+    let user = db.set_user_role(user.id, role).await?;
+    info!("User {} is now {:?}", user.email, user.role);
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use log::info;
+
+use crate::cli::SeedArgs;
+use crate::config::WamConfig;
+use crate::database::WamDatabase;
+
+const DEMO_USERS: [(&str, &str); 3] = [
+    ("Alice", "alice@example.com"),
+    ("Bob", "bob@example.com"),
+    ("Carol", "carol@example.com"),
+];
+
+/// Create the demo users that do not exist yet, and a few messages for each of them.
+/// Messages are keyed by their number, so running it again only adds the missing ones.
+pub async fn run(config: &WamConfig, args: &SeedArgs) -> anyhow::Result<()> {
+    let db = WamDatabase::open(&config.database).await?;
+
+    for (name, email) in DEMO_USERS {
+        let user = match db.get_user_by_email(email).await? {
+            Some(user) => user,
+            None => {
+                db.create_user(entity::user::Model {
+                    id: 0,
+                    name: name.to_string(),
+                    email: email.to_string(),
+                    password_hash: None,
+                    role: Default::default(),
+                    tombstone: false,
+                    token_version: 0,
+                    created_at: Default::default(),
+                    updated_at: Default::default(),
+                }).await?
+            }
+        };
+
+        let mut created = 0;
+        for i in 1..=args.messages_per_user {
+            let stored = db.create_message(&entity::message::Model {
+                id: 0,
+                text: format!("Hello from {} #{}", user.name, i),
+                user_id: user.id,
+                client_msg_id: Some(format!("seed-{}", i)),
+                parent_id: None,
+                channel_id: None,
+                source: None,
+                created_at: Default::default(),
+                updated_at: Default::default(),
+            }).await?;
+            if stored.created {
+                created += 1;
+            }
+        }
+        info!("Seeded user {} with {} new message(s)", user.email, created);
+    }
+
+    Ok(())
+}
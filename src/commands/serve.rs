@@ -0,0 +1,158 @@
+use anyhow::Context;
+use axum::Router;
+use log::{error, info, warn};
+use std::io;
+#[cfg(feature = "tls")]
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::{ListenerConfig, WamConfig};
+use crate::{build_listener_app, shutdown, WamServerState};
+
+pub async fn run(config: WamConfig) -> anyhow::Result<()> {
+
+    let state = WamServerState::builder()
+        .config(config)
+        .build()
+        .await
+        .context("cannot open the database")?;
+
+    let mut background_tasks = state.spawn_background_tasks();
+
+    // run it
+    let mut servers: JoinSet<io::Result<()>> = JoinSet::new();
+    #[cfg(feature = "tls")]
+    let rustls = load_tls(&state).await?;
+
+    for listener in state.config.listeners() {
+        let app = build_listener_app(state.clone(), listener.routes);
+        let token = state.shutdown.clone();
+
+        #[cfg(feature = "tls")]
+        if listener.tls {
+            let rustls = rustls.clone().context("TLS listener without server.tls")?;
+            spawn_tls_server(&mut servers, &listener, app, rustls, token)?;
+            continue;
+        }
+
+        spawn_server(&mut servers, &listener, app, token).await?;
+    }
+
+    tokio::select! {
+        _ = shutdown::wait_for_signal() => {}
+        res = servers.join_next() => {
+            error!("Server stopped unexpectedly: {:?}", res);
+        }
+    }
+    shutdown::trigger(&state);
+
+    // Let connections drain and background tasks finish their current work, within the deadline
+    let deadline = Duration::from_secs(state.config.server.shutdown_timeout_secs);
+    let drain = async {
+        while servers.join_next().await.is_some() {}
+        while background_tasks.join_next().await.is_some() {}
+    };
+
+    if tokio::time::timeout(deadline, drain).await.is_err() {
+        warn!("Shutdown deadline of {:?} exceeded, aborting remaining tasks", deadline);
+        servers.abort_all();
+        background_tasks.abort_all();
+    }
+    info!("Server stopped");
+
+    Ok(())
+}
+
+/// Plain HTTP server on TCP or a unix socket, stops accepting connections as soon as shutdown is triggered.
+async fn spawn_server(servers: &mut JoinSet<io::Result<()>>, listener: &ListenerConfig, app: Router, token: CancellationToken) -> anyhow::Result<()> {
+    if let Some(address) = &listener.address {
+        let tcp = tokio::net::TcpListener::bind(address)
+            .await
+            .with_context(|| format!("cannot bind listener '{}' on {}", listener.name, address))?;
+        println!("listening on {} ({}, {:?} routes)", tcp.local_addr()?, listener.name, listener.routes);
+
+        servers.spawn(async move {
+            axum::serve(tcp, app)
+                .with_graceful_shutdown(token.cancelled_owned())
+                .await
+        });
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    if let Some(path) = &listener.unix_socket {
+        remove_stale_socket(path)
+            .with_context(|| format!("cannot reuse the path of listener '{}': {}", listener.name, path))?;
+        let unix = tokio::net::UnixListener::bind(path)
+            .with_context(|| format!("cannot bind listener '{}' on {}", listener.name, path))?;
+        println!("listening on unix:{} ({}, {:?} routes)", path, listener.name, listener.routes);
+
+        servers.spawn(async move {
+            axum::serve(unix, app)
+                .with_graceful_shutdown(token.cancelled_owned())
+                .await
+        });
+        return Ok(());
+    }
+
+    anyhow::bail!("listener '{}' has no address", listener.name)
+}
+
+/// Remove a socket file left by a previous run, which would make bind fail. Anything else at
+/// the path is left alone and reported.
+#[cfg(unix)]
+fn remove_stale_socket(path: &str) -> anyhow::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => Ok(std::fs::remove_file(path)?),
+        Ok(_) => anyhow::bail!("a file that is not a socket already exists there"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Load the certificate when a listener needs it, and start watching it for changes.
+#[cfg(feature = "tls")]
+async fn load_tls(state: &WamServerState) -> anyhow::Result<Option<axum_server::tls_rustls::RustlsConfig>> {
+    let Some(tls) = state.config.server.tls.clone() else {
+        return Ok(None);
+    };
+    if !state.config.listeners().iter().any(|l| l.tls) {
+        return Ok(None);
+    }
+
+    let rustls = crate::tls::load(&tls).await?;
+    tokio::spawn(crate::tls::watch(rustls.clone(), tls, state.shutdown.clone()));
+    Ok(Some(rustls))
+}
+
+/// HTTPS server (WebSocket upgrades become WSS), sharing the hot reloaded certificate.
+#[cfg(feature = "tls")]
+fn spawn_tls_server(
+    servers: &mut JoinSet<io::Result<()>>,
+    listener: &ListenerConfig,
+    app: Router,
+    rustls: axum_server::tls_rustls::RustlsConfig,
+    token: CancellationToken,
+) -> anyhow::Result<()> {
+    let address = listener.address.as_deref().context("TLS listener without address")?;
+    let addr: SocketAddr = address.parse()?;
+
+    let handle = axum_server::Handle::new();
+    let shutdown_handle = handle.clone();
+    tokio::spawn(async move {
+        token.cancelled().await;
+        shutdown_handle.graceful_shutdown(None);
+    });
+
+    println!("listening on https://{} ({}, {:?} routes)", addr, listener.name, listener.routes);
+    servers.spawn(
+        axum_server::bind_rustls(addr, rustls)
+            .handle(handle)
+            .serve(app.into_make_service()),
+    );
+    Ok(())
+}
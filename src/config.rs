@@ -0,0 +1,463 @@
+use std::{collections::HashMap, env, fmt, fs, path::Path};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+/// Default location of the configuration file, overridable with `WAM_CONFIG`.
+const DEFAULT_CONFIG_PATH: &str = "wamserver.toml";
+
+/// Whole server configuration.
+/// Loaded once at startup from a TOML file, then patched with environment variables.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WamConfig {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub kafka: KafkaConfig,
+    pub sytral: SytralConfig,
+    pub auth: AuthConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Address of the default listener, used when `listeners` is empty
+    pub bind_address: String,
+    /// When set (and `listeners` is empty), admin routes move to a second listener on this address
+    pub admin_bind_address: Option<String>,
+    /// Explicit listeners, replacing `bind_address`/`admin_bind_address`
+    pub listeners: Vec<ListenerConfig>,
+    /// Maximum time given to connections and background tasks to stop on SIGTERM/SIGINT.
+    pub shutdown_timeout_secs: u64,
+    /// Period of the `stats` WebSocket event, 0 to disable it.
+    pub stats_interval_secs: u64,
+    /// Serve HTTPS/WSS instead of plain HTTP when set.
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListenerConfig {
+    pub name: String,
+    /// TCP `host:port`
+    pub address: Option<String>,
+    /// Unix domain socket path, instead of `address`
+    pub unix_socket: Option<String>,
+    pub routes: ListenerRoutes,
+    /// Use the `server.tls` certificate on this (TCP) listener
+    pub tls: bool,
+}
+
+/// Which routes a listener serves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListenerRoutes {
+    /// Public and admin routes
+    #[default]
+    All,
+    /// API, WebSocket and frontend
+    Public,
+    /// Metrics, health checks and operational endpoints only
+    Admin,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM certificate chain
+    pub cert_path: String,
+    /// PEM private key
+    pub key_path: String,
+    /// How often the files are checked for changes, 0 disables hot reload.
+    pub reload_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    /// What deleting a user does to their messages by default, applied by the server on top of a
+    /// restricting foreign key.
+    pub on_user_delete: OnUserDelete,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnUserDelete {
+    /// Refuse to delete users that still have messages
+    #[default]
+    Restrict,
+    /// Delete their messages along with them
+    Cascade,
+    /// Hand their messages over to a shared "Deleted user"
+    Tombstone,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KafkaConfig {
+    pub enabled: bool,
+    pub url: String,
+    pub topic: String,
+    pub group: String,
+    pub vehicles_topic: String,
+    pub poll_interval_secs: u64,
+    /// Extra topics whose messages are posted in a channel, by channel name
+    pub channel_topics: HashMap<String, String>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SytralConfig {
+    pub enabled: bool,
+    pub url: String,
+    pub username: String,
+    pub password: String,
+    pub poll_interval_secs: u64,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// HMAC secret of the session tokens, a random one is drawn at startup when empty
+    pub jwt_secret: String,
+    pub access_token_ttl_secs: u64,
+    pub refresh_token_ttl_secs: u64,
+}
+
+/// Stands for a secret in `Debug` output, an empty one is shown as is.
+fn redacted(secret: &str) -> &'static str {
+    if secret.is_empty() { "" } else { "<redacted>" }
+}
+
+impl fmt::Debug for SytralConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SytralConfig")
+            .field("enabled", &self.enabled)
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &redacted(&self.password))
+            .field("poll_interval_secs", &self.poll_interval_secs)
+            .finish()
+    }
+}
+
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("jwt_secret", &redacted(&self.jwt_secret))
+            .field("access_token_ttl_secs", &self.access_token_ttl_secs)
+            .field("refresh_token_ttl_secs", &self.refresh_token_ttl_secs)
+            .finish()
+    }
+}
+
+/// All the problems found while loading the configuration, reported together.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_address: "0.0.0.0:3000".to_string(),
+            admin_bind_address: None,
+            listeners: Vec::new(),
+            shutdown_timeout_secs: 10,
+            stats_interval_secs: 5,
+            tls: None,
+        }
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        TlsConfig {
+            cert_path: String::new(),
+            key_path: String::new(),
+            reload_interval_secs: 30,
+        }
+    }
+}
+
+impl Default for KafkaConfig {
+    fn default() -> Self {
+        KafkaConfig {
+            enabled: true,
+            url: String::new(),
+            topic: String::new(),
+            group: String::new(),
+            vehicles_topic: "vehicles".to_string(),
+            poll_interval_secs: 5,
+            channel_topics: HashMap::new(),
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            jwt_secret: String::new(),
+            access_token_ttl_secs: 15 * 60,
+            refresh_token_ttl_secs: 7 * 24 * 3600,
+        }
+    }
+}
+
+impl Default for SytralConfig {
+    fn default() -> Self {
+        SytralConfig {
+            enabled: true,
+            url: "https://data.grandlyon.com/siri-lite/2.0/vehicle-monitoring.json".to_string(),
+            username: String::new(),
+            password: String::new(),
+            poll_interval_secs: 5,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "invalid configuration ({} problem(s)):", self.problems.len())?;
+        for problem in &self.problems {
+            writeln!(f, "  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl WamConfig {
+    /// Load the configuration file (if any), apply environment overrides and validate the result.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::load_with(|_| {})
+    }
+
+    /// Same as `load`, with command line overrides applied before validation.
+    pub fn load_with(overrides: impl FnOnce(&mut WamConfig)) -> Result<Self, ConfigError> {
+        let path = env::var("WAM_CONFIG").ok();
+        let mut problems = Vec::new();
+
+        let mut config = match &path {
+            Some(path) => Self::from_file(Path::new(path), &mut problems),
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH), &mut problems)
+            }
+            None => WamConfig::default(),
+        };
+
+        config.apply_env(&mut problems);
+        overrides(&mut config);
+        config.apply_features();
+        config.validate(&mut problems);
+
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError { problems })
+        }
+    }
+
+    fn from_file(path: &Path, problems: &mut Vec<String>) -> Self {
+        info!("Loading configuration from {}", path.display());
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                problems.push(format!("cannot read {}: {}", path.display(), e));
+                return WamConfig::default();
+            }
+        };
+
+        toml::from_str(&content).unwrap_or_else(|e| {
+            problems.push(format!("cannot parse {}: {}", path.display(), e));
+            WamConfig::default()
+        })
+    }
+
+    /// Environment variables win over the file, so existing deployments keep working.
+    fn apply_env(&mut self, problems: &mut Vec<String>) {
+        override_string("WAM_BIND_ADDRESS", &mut self.server.bind_address);
+        if let Ok(value) = env::var("WAM_ADMIN_BIND_ADDRESS") {
+            self.server.admin_bind_address = Some(value);
+        }
+        override_number("WAM_SHUTDOWN_TIMEOUT_SECS", &mut self.server.shutdown_timeout_secs, problems);
+        override_number("WAM_STATS_INTERVAL_SECS", &mut self.server.stats_interval_secs, problems);
+        if env::var("WAM_TLS_CERT_PATH").is_ok() || env::var("WAM_TLS_KEY_PATH").is_ok() {
+            let tls = self.server.tls.get_or_insert_with(TlsConfig::default);
+            override_string("WAM_TLS_CERT_PATH", &mut tls.cert_path);
+            override_string("WAM_TLS_KEY_PATH", &mut tls.key_path);
+        }
+        if let Some(tls) = self.server.tls.as_mut() {
+            override_number("WAM_TLS_RELOAD_INTERVAL_SECS", &mut tls.reload_interval_secs, problems);
+        }
+        override_string("DATABASE_URL", &mut self.database.url);
+        if let Ok(value) = env::var("DATABASE_ON_USER_DELETE") {
+            match value.to_ascii_lowercase().as_str() {
+                "restrict" => self.database.on_user_delete = OnUserDelete::Restrict,
+                "cascade" => self.database.on_user_delete = OnUserDelete::Cascade,
+                "tombstone" => self.database.on_user_delete = OnUserDelete::Tombstone,
+                _ => problems.push(format!("DATABASE_ON_USER_DELETE must be restrict, cascade or tombstone, got '{}'", value)),
+            }
+        }
+        override_bool("KAFKA_ENABLED", &mut self.kafka.enabled, problems);
+        override_string("KAFKA_URL", &mut self.kafka.url);
+        override_string("KAFKA_TOPIC", &mut self.kafka.topic);
+        override_string("KAFKA_GROUP", &mut self.kafka.group);
+        override_string("KAFKA_VEHICLES_TOPIC", &mut self.kafka.vehicles_topic);
+        override_number("KAFKA_POLL_INTERVAL_SECS", &mut self.kafka.poll_interval_secs, problems);
+        override_bool("SYTRAL_ENABLED", &mut self.sytral.enabled, problems);
+        override_string("SYTRAL_URL", &mut self.sytral.url);
+        override_string("SYTRAL_USERNAME", &mut self.sytral.username);
+        override_string("SYTRAL_PASSWORD", &mut self.sytral.password);
+        override_number("SYTRAL_POLL_INTERVAL_SECS", &mut self.sytral.poll_interval_secs, problems);
+        override_string("WAM_JWT_SECRET", &mut self.auth.jwt_secret);
+        override_number("WAM_ACCESS_TOKEN_TTL_SECS", &mut self.auth.access_token_ttl_secs, problems);
+        override_number("WAM_REFRESH_TOKEN_TTL_SECS", &mut self.auth.refresh_token_ttl_secs, problems);
+    }
+
+    /// Integrations left out of the build are always disabled.
+    fn apply_features(&mut self) {
+        if self.kafka.enabled && !cfg!(feature = "kafka") {
+            info!("Kafka support is not compiled in, Kafka integration disabled");
+            self.kafka.enabled = false;
+        }
+        if self.sytral.enabled && !cfg!(feature = "sytral") {
+            info!("SYTRAL support is not compiled in, SYTRAL integration disabled");
+            self.sytral.enabled = false;
+        }
+    }
+
+    /// Listeners to bind: the explicit ones, or those derived from `bind_address`/`admin_bind_address`.
+    pub fn listeners(&self) -> Vec<ListenerConfig> {
+        let server = &self.server;
+        if !server.listeners.is_empty() {
+            return server.listeners.clone();
+        }
+
+        let mut listeners = vec![ListenerConfig {
+            name: "main".to_string(),
+            address: Some(server.bind_address.clone()),
+            unix_socket: None,
+            routes: if server.admin_bind_address.is_some() { ListenerRoutes::Public } else { ListenerRoutes::All },
+            tls: server.tls.is_some(),
+        }];
+        if let Some(admin) = &server.admin_bind_address {
+            listeners.push(ListenerConfig {
+                name: "admin".to_string(),
+                address: Some(admin.clone()),
+                unix_socket: None,
+                routes: ListenerRoutes::Admin,
+                tls: false,
+            });
+        }
+        listeners
+    }
+
+    fn validate(&self, problems: &mut Vec<String>) {
+        let mut names = std::collections::HashSet::new();
+        for listener in self.listeners() {
+            let name = &listener.name;
+            if !names.insert(name.clone()) {
+                problems.push(format!("listener name '{}' is used more than once", name));
+            }
+            match (&listener.address, &listener.unix_socket) {
+                (Some(address), None) => {
+                    if address.parse::<std::net::SocketAddr>().is_err() {
+                        problems.push(format!("listener '{}': address '{}' is not a valid socket address", name, address));
+                    }
+                }
+                (None, Some(path)) => {
+                    if !cfg!(unix) {
+                        problems.push(format!("listener '{}': unix sockets are not supported on this platform", name));
+                    } else if listener.tls {
+                        problems.push(format!("listener '{}': tls is not supported on unix sockets", name));
+                    } else if path.trim().is_empty() {
+                        problems.push(format!("listener '{}': unix_socket must not be empty", name));
+                    }
+                }
+                _ => problems.push(format!("listener '{}': set exactly one of address or unix_socket", name)),
+            }
+            if listener.tls && self.server.tls.is_none() {
+                problems.push(format!("listener '{}': tls requires server.tls", name));
+            }
+        }
+
+        if let Some(tls) = &self.server.tls {
+            if !cfg!(feature = "tls") {
+                problems.push("server.tls is set but TLS support is not compiled in".to_string());
+            }
+            for (name, path) in [
+                ("server.tls.cert_path (WAM_TLS_CERT_PATH)", &tls.cert_path),
+                ("server.tls.key_path (WAM_TLS_KEY_PATH)", &tls.key_path),
+            ] {
+                if path.trim().is_empty() {
+                    problems.push(format!("{} must be set", name));
+                } else if !Path::new(path).is_file() {
+                    problems.push(format!("{} '{}' is not a readable file", name, path));
+                }
+            }
+        }
+
+        require("database.url (DATABASE_URL)", &self.database.url, problems);
+
+        if !self.auth.jwt_secret.is_empty() && self.auth.jwt_secret.len() < 32 {
+            problems.push("auth.jwt_secret (WAM_JWT_SECRET) must be at least 32 bytes long".to_string());
+        }
+        if self.auth.access_token_ttl_secs == 0 || self.auth.refresh_token_ttl_secs == 0 {
+            problems.push("auth token lifetimes must be greater than 0".to_string());
+        }
+
+        // Disabled integrations do not need to be configured
+        if self.kafka.enabled {
+            require("kafka.url (KAFKA_URL)", &self.kafka.url, problems);
+            require("kafka.topic (KAFKA_TOPIC)", &self.kafka.topic, problems);
+            require("kafka.group (KAFKA_GROUP)", &self.kafka.group, problems);
+            require("kafka.vehicles_topic (KAFKA_VEHICLES_TOPIC)", &self.kafka.vehicles_topic, problems);
+            if self.kafka.poll_interval_secs == 0 {
+                problems.push("kafka.poll_interval_secs must be greater than 0".to_string());
+            }
+        }
+
+        if self.sytral.enabled {
+            require("sytral.url (SYTRAL_URL)", &self.sytral.url, problems);
+            require("sytral.username (SYTRAL_USERNAME)", &self.sytral.username, problems);
+            require("sytral.password (SYTRAL_PASSWORD)", &self.sytral.password, problems);
+            if self.sytral.poll_interval_secs == 0 {
+                problems.push("sytral.poll_interval_secs must be greater than 0".to_string());
+            }
+        }
+    }
+}
+
+fn override_string(var: &str, target: &mut String) {
+    if let Ok(value) = env::var(var) {
+        *target = value;
+    }
+}
+
+fn override_number(var: &str, target: &mut u64, problems: &mut Vec<String>) {
+    if let Ok(value) = env::var(var) {
+        match value.parse() {
+            Ok(parsed) => *target = parsed,
+            Err(_) => problems.push(format!("{} must be a positive integer, got '{}'", var, value)),
+        }
+    }
+}
+
+fn override_bool(var: &str, target: &mut bool, problems: &mut Vec<String>) {
+    if let Ok(value) = env::var(var) {
+        match value.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => *target = true,
+            "0" | "false" | "no" | "off" => *target = false,
+            _ => problems.push(format!("{} must be a boolean, got '{}'", var, value)),
+        }
+    }
+}
+
+fn require(name: &str, value: &str, problems: &mut Vec<String>) {
+    if value.trim().is_empty() {
+        problems.push(format!("{} must be set", name));
+    }
+}
@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use sea_orm::*;
+use ::entity::api_key as api_key;
+
+use crate::database::WamDatabase;
+
+impl WamDatabase {
+    /// Store a new key of the user, only its hash and prefix are kept.
+    pub async fn create_api_key(&self, user_id: i32, key: api_key::Model, prefix: String, key_hash: String) -> Result<api_key::Model, DbErr> {
+        api_key::ActiveModel {
+            user_id: Set(user_id),
+            name: Set(key.name),
+            prefix: Set(prefix),
+            key_hash: Set(key_hash),
+            scopes: Set(key.scopes),
+            expires_at: Set(key.expires_at),
+            ..Default::default()
+        }
+        .insert(&self.conn)
+        .await
+    }
+
+    pub async fn get_api_keys(&self, user_id: i32) -> Result<Vec<api_key::Model>, DbErr> {
+        api_key::Entity::find()
+            .filter(api_key::Column::UserId.eq(user_id))
+            .order_by_asc(api_key::Column::Id)
+            .all(&self.conn)
+            .await
+    }
+
+    pub async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<Option<api_key::Model>, DbErr> {
+        api_key::Entity::find()
+            .filter(api_key::Column::KeyHash.eq(key_hash))
+            .one(&self.conn)
+            .await
+    }
+
+    /// Revoke a key, only its owner may do so.
+    pub async fn delete_api_key(&self, id: i32, user_id: i32) -> Result<(), DbErr> {
+        let res = api_key::Entity::delete_many()
+            .filter(api_key::Column::Id.eq(id))
+            .filter(api_key::Column::UserId.eq(user_id))
+            .exec(&self.conn)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(DbErr::RecordNotFound(format!("API key with id {} not found", id)));
+        }
+        Ok(())
+    }
+
+    pub async fn touch_api_key(&self, id: i32, at: DateTime<Utc>) -> Result<(), DbErr> {
+        api_key::Entity::update_many()
+            .col_expr(api_key::Column::LastUsedAt, sea_query::Expr::value(at))
+            .filter(api_key::Column::Id.eq(id))
+            .exec(&self.conn)
+            .await?;
+        Ok(())
+    }
+}
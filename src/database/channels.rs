@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use sea_orm::*;
+use ::entity::channel as channel;
+use ::entity::channel_member as channel_member;
+use ::entity::user as user;
+
+use crate::database::WamDatabase;
+
+impl WamDatabase {
+    pub async fn create_channel(&self, name: String) -> Result<channel::Model, DbErr> {
+        channel::ActiveModel {
+            name: Set(name),
+            ..Default::default()
+        }
+        .insert(&self.conn)
+        .await
+    }
+
+    pub async fn get_channels(&self) -> Result<Vec<channel::Model>, DbErr> {
+        channel::Entity::find()
+            .order_by_asc(channel::Column::Id)
+            .all(&self.conn)
+            .await
+    }
+
+    pub async fn get_channel(&self, channel_id: i32) -> Result<channel::Model, DbErr> {
+        channel::Entity::find_by_id(channel_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(DbErr::RecordNotFound(format!("Channel with id {} not found", channel_id)))
+    }
+
+    /// Which of the given ids belong to an existing channel, in a single query.
+    pub async fn existing_channel_ids(&self, ids: &[i32]) -> Result<HashSet<i32>, DbErr> {
+        let ids = channel::Entity::find()
+            .select_only()
+            .column(channel::Column::Id)
+            .filter(channel::Column::Id.is_in(ids.iter().copied()))
+            .into_tuple::<i32>()
+            .all(&self.conn)
+            .await?;
+        Ok(ids.into_iter().collect())
+    }
+
+    pub async fn get_channel_by_name(&self, name: &str) -> Result<Option<channel::Model>, DbErr> {
+        channel::Entity::find()
+            .filter(channel::Column::Name.eq(name))
+            .one(&self.conn)
+            .await
+    }
+
+    pub async fn add_channel_member(&self, channel_id: i32, user_id: i32) -> Result<channel_member::Model, DbErr> {
+        channel_member::ActiveModel {
+            channel_id: Set(channel_id),
+            user_id: Set(user_id),
+            ..Default::default()
+        }
+        .insert(&self.conn)
+        .await
+    }
+
+    pub async fn remove_channel_member(&self, channel_id: i32, user_id: i32) -> Result<(), DbErr> {
+        let res = channel_member::Entity::delete_by_id((channel_id, user_id))
+            .exec(&self.conn)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(DbErr::RecordNotFound(format!("User {} is not a member of channel {}", user_id, channel_id)));
+        }
+        Ok(())
+    }
+
+    pub async fn get_channel_members(&self, channel_id: i32) -> Result<Vec<user::Model>, DbErr> {
+        self.get_channel(channel_id).await?
+            .find_related(user::Entity)
+            .order_by_asc(user::Column::Id)
+            .all(&self.conn)
+            .await
+    }
+
+    pub async fn get_user_channel_ids(&self, user_id: i32) -> Result<HashSet<i32>, DbErr> {
+        let ids = channel_member::Entity::find()
+            .select_only()
+            .column(channel_member::Column::ChannelId)
+            .filter(channel_member::Column::UserId.eq(user_id))
+            .into_tuple::<i32>()
+            .all(&self.conn)
+            .await?;
+        Ok(ids.into_iter().collect())
+    }
+
+    pub async fn is_channel_member(&self, channel_id: i32, user_id: i32) -> Result<bool, DbErr> {
+        let member = channel_member::Entity::find_by_id((channel_id, user_id))
+            .one(&self.conn)
+            .await?;
+        Ok(member.is_some())
+    }
+
+    /// Existing `(channel_id, user_id)` memberships of the given channels, in a single query.
+    pub async fn get_memberships(&self, channel_ids: &[i32]) -> Result<HashSet<(i32, i32)>, DbErr> {
+        let pairs = channel_member::Entity::find()
+            .select_only()
+            .column(channel_member::Column::ChannelId)
+            .column(channel_member::Column::UserId)
+            .filter(channel_member::Column::ChannelId.is_in(channel_ids.iter().copied()))
+            .into_tuple::<(i32, i32)>()
+            .all(&self.conn)
+            .await?;
+        Ok(pairs.into_iter().collect())
+    }
+}
@@ -1,8 +1,12 @@
-use std::env;
 use log::info;
-use sea_orm::{Database, DatabaseConnection};
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, DbBackend, DbErr};
 use migration::{Migrator, MigratorTrait};
 
+use crate::config::DatabaseConfig;
+
+mod api_keys;
+mod channels;
+mod stats;
 pub mod requests;
 
 #[derive(Clone)]
@@ -11,13 +15,25 @@ pub struct WamDatabase {
 }
 
 impl WamDatabase {
-    pub async fn open() -> Self {
+    /// Connect and apply pending migrations.
+    pub async fn open(config: &DatabaseConfig) -> Result<Self, DbErr> {
+        let db = Self::connect(config).await?;
+        Migrator::up(&db.conn, None).await?;
+
+        Ok(db)
+    }
+
+    /// Connect without touching the schema, for the `migrate` command.
+    pub async fn connect(config: &DatabaseConfig) -> Result<Self, DbErr> {
         
-        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-        info!("Opening database at {}", database_url);
-        let conn = Database::connect(database_url).await.expect("Failed to connect to the database");
-        Migrator::up(&conn, None).await.expect("Failed to run migrations");
+        info!("Opening database at {}", config.url);
+        let conn = Database::connect(&config.url).await?;
 
-        WamDatabase { conn }
+        Ok(WamDatabase { conn })
     }
-}
\ No newline at end of file
+
+    /// Full-text search and message rates are written for SQLite only.
+    pub fn is_sqlite(&self) -> bool {
+        self.conn.get_database_backend() == DbBackend::Sqlite
+    }
+}
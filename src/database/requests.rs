@@ -1,48 +1,464 @@
+use std::collections::HashMap;
+
 use sea_orm::*; 
+use sea_orm::prelude::DateTimeUtc;
 use ::entity::message as message;
 use ::entity::user as user;
+use ::entity::channel_member as channel_member;
 
+use crate::config::OnUserDelete;
 use crate::database::WamDatabase;
 
+/// Email of the user that inherits the messages of deleted users under `OnUserDelete::Tombstone`
+const TOMBSTONE_EMAIL: &str = "deleted-user@wam.invalid";
+const TOMBSTONE_NAME: &str = "Deleted user";
+
+/// Filters and cursor of a message listing. Every field is optional.
+#[derive(Debug, Clone, Default)]
+pub struct MessageFilter {
+    /// Channels whose messages may be returned
+    pub visibility: Visibility,
+    pub user_id: Option<i32>,
+    pub channel_id: Option<i32>,
+    /// Substring of the message text
+    pub contains: Option<String>,
+    /// Only messages with a greater id (newer)
+    pub after: Option<i32>,
+    /// Only messages with a smaller id (older)
+    pub before: Option<i32>,
+    /// Only messages created at or after this time
+    pub since: Option<DateTimeUtc>,
+    /// Only messages created before this time
+    pub until: Option<DateTimeUtc>,
+    /// Page size, everything matching when `None`
+    pub limit: Option<u64>,
+}
+
+/// Which messages a reader may see. Messages without a channel are public.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Visibility {
+    /// Every message, for admins and internal uses
+    #[default]
+    All,
+    /// Public messages, plus those of the channels the user joined
+    Member(i32),
+    /// Public messages only, for anonymous readers
+    Public,
+}
+
+impl Visibility {
+    /// Restriction on `message.channel_id`, `None` when everything is visible
+    pub(super) fn condition(self) -> Option<Condition> {
+        let public = message::Column::ChannelId.is_null();
+        match self {
+            Visibility::All => None,
+            Visibility::Public => Some(Condition::all().add(public)),
+            Visibility::Member(user_id) => Some(Condition::any()
+                .add(public)
+                .add(message::Column::ChannelId.in_subquery(sea_query::Query::select()
+                    .column(channel_member::Column::ChannelId)
+                    .from(channel_member::Entity)
+                    .and_where(channel_member::Column::UserId.eq(user_id))
+                    .to_owned()))),
+        }
+    }
+
+    /// The same restriction in raw SQL, binding the user id as `$4`
+    pub(super) fn sql(self) -> &'static str {
+        match self {
+            Visibility::All => "",
+            Visibility::Public => "AND message.channel_id IS NULL",
+            Visibility::Member(_) => "AND (message.channel_id IS NULL OR message.channel_id IN (SELECT channel_id FROM channel_member WHERE user_id = $4))",
+        }
+    }
+}
+
+/// Filters and cursor of a user listing. Every field is optional.
+#[derive(Debug, Clone, Default)]
+pub struct UserFilter {
+    /// Substring of the name or email
+    pub search: Option<String>,
+    /// Only users created at or after this time
+    pub since: Option<DateTimeUtc>,
+    /// Only users created before this time
+    pub until: Option<DateTimeUtc>,
+    /// Only users with a greater id
+    pub after: Option<i32>,
+    /// Page size, everything matching when `None`
+    pub limit: Option<u64>,
+}
+
+/// A page of users, by increasing id.
+#[derive(Debug, Clone)]
+pub struct UserPage {
+    pub users: Vec<user::Model>,
+    /// Pass as `after` to get the next page
+    pub next_cursor: Option<i32>,
+}
+
+/// A deleted user and what became of their messages.
+#[derive(Debug, Clone)]
+pub struct DeletedUser {
+    pub user: user::Model,
+    /// Messages deleted or reassigned along with the user
+    pub messages: u64,
+    /// The tombstone user now owning the messages, under `OnUserDelete::Tombstone`
+    pub reassigned_to: Option<i32>,
+}
+
+/// A message returned by an idempotent insert.
+#[derive(Debug, Clone)]
+pub struct StoredMessage {
+    pub message: message::Model,
+    /// `false` when the idempotency key had already been used, `message` is then the original row
+    pub created: bool,
+}
+
+/// A page of messages, newest first.
+#[derive(Debug, Clone)]
+pub struct MessagePage {
+    pub messages: Vec<message::Model>,
+    /// Pass as `before` to get the next (older) page
+    pub next_cursor: Option<i32>,
+    /// Pass as `after` to get the previous (newer) page
+    pub prev_cursor: Option<i32>,
+}
+
+/// A full-text search match, best matches come first.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub message: message::Model,
+    /// Excerpt of the text around the match, HTML-escaped with matched terms wrapped in `<mark>`
+    pub snippet: String,
+    /// Relevance, higher is better. Only comparable within one search.
+    pub score: f64,
+}
+
+const SQLITE_SEARCH: &str = "
+    SELECT message.*,
+        snippet(message_fts, 0, char(2), char(3), '…', 16) AS snippet,
+        -bm25(message_fts) AS score
+    FROM message_fts JOIN message ON message.id = message_fts.rowid
+    WHERE message_fts MATCH $1 {visible}
+    ORDER BY bm25(message_fts), message.id DESC
+    LIMIT $2 OFFSET $3";
+
+/// Walks up from each message to the top of its thread
+const THREAD_ROOTS: &str = "
+    WITH RECURSIVE chain(start_id, id, parent_id) AS (
+        SELECT id, id, parent_id FROM message WHERE id IN ({ids})
+        UNION ALL
+        SELECT chain.start_id, message.id, message.parent_id
+        FROM message JOIN chain ON message.id = chain.parent_id
+    )
+    SELECT start_id, id AS root_id FROM chain WHERE parent_id IS NULL";
+
+/// A message and all its replies, at any depth
+const THREAD: &str = "
+    WITH RECURSIVE thread AS (
+        SELECT * FROM message WHERE id = $1
+        UNION ALL
+        SELECT message.* FROM message JOIN thread ON message.parent_id = thread.id
+    )
+    SELECT * FROM thread ORDER BY id";
+
 impl WamDatabase {
-    pub async fn create_message(&self, msg: &message::Model)-> Result<message::Model, DbErr> {
-        message::ActiveModel{
-                    text: Set(msg.text.clone()),
-                    user_id: Set(msg.user_id),
-                    ..Default::default()
+    pub async fn ping(&self) -> Result<(), DbErr> {
+        self.conn.ping().await
+    }
+
+    /// Insert a message, unless its author already used its `client_msg_id`: the original row is returned then.
+    pub async fn create_message(&self, msg: &message::Model)-> Result<StoredMessage, DbErr> {
+        if let Some(existing) = self.get_message_by_client_msg_id(msg.user_id, msg.client_msg_id.as_deref()).await? {
+            return Ok(StoredMessage { message: existing, created: false });
         }
-        .insert(&self.conn)
-        .await
+
+        match new_message(msg).insert(&self.conn).await {
+            Ok(message) => Ok(StoredMessage { message, created: true }),
+            // A concurrent delivery of the same key won the race
+            Err(e) if matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) => {
+                match self.get_message_by_client_msg_id(msg.user_id, msg.client_msg_id.as_deref()).await? {
+                    Some(existing) => Ok(StoredMessage { message: existing, created: false }),
+                    None => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn get_message_by_client_msg_id(&self, user_id: i32, client_msg_id: Option<&str>) -> Result<Option<message::Model>, DbErr> {
+        let Some(client_msg_id) = client_msg_id else {
+            return Ok(None);
+        };
+        message::Entity::find()
+            .filter(message::Column::UserId.eq(user_id))
+            .filter(message::Column::ClientMsgId.eq(client_msg_id))
+            .one(&self.conn)
+            .await
+    }
+
+    /// Messages of the user already stored under one of the given idempotency keys, by key.
+    pub async fn get_messages_by_client_msg_ids(&self, user_id: i32, client_msg_ids: &[String]) -> Result<HashMap<String, message::Model>, DbErr> {
+        let messages = message::Entity::find()
+            .filter(message::Column::UserId.eq(user_id))
+            .filter(message::Column::ClientMsgId.is_in(client_msg_ids.iter().cloned()))
+            .all(&self.conn)
+            .await?;
+        Ok(messages.into_iter()
+            .filter_map(|m| Some((m.client_msg_id.clone()?, m)))
+            .collect())
+    }
+
+    /// Insert all the messages in one transaction, nothing is inserted if one of them fails.
+    pub async fn create_messages(&self, msgs: &[message::Model]) -> Result<Vec<message::Model>, DbErr> {
+        let txn = self.conn.begin().await?;
+        let mut saved = Vec::with_capacity(msgs.len());
+        for msg in msgs {
+            saved.push(new_message(msg).insert(&txn).await?);
+        }
+        txn.commit().await?;
+        Ok(saved)
     }
 
     pub async fn create_user(&self, user: user::Model)-> Result<user::Model, DbErr> {
         user::ActiveModel{
                     name: Set(user.name),
                     email: Set(user.email),
+                    password_hash: Set(user.password_hash),
+                    role: Set(user.role),
                     ..Default::default()
         }
         .insert(&self.conn)
         .await
     }
 
-    pub async fn get_messages(&self) -> Result<Vec<message::Model>, DbErr> {
-        message::Entity::find()
+    pub async fn get_messages(&self, filter: &MessageFilter) -> Result<MessagePage, DbErr> {
+        let mut query = message::Entity::find();
+        if let Some(condition) = filter.visibility.condition() {
+            query = query.filter(condition);
+        }
+        if let Some(user_id) = filter.user_id {
+            query = query.filter(message::Column::UserId.eq(user_id));
+        }
+        if let Some(channel_id) = filter.channel_id {
+            query = query.filter(message::Column::ChannelId.eq(channel_id));
+        }
+        if let Some(text) = &filter.contains {
+            query = query.filter(message::Column::Text.contains(text));
+        }
+        if let Some(since) = filter.since {
+            query = query.filter(message::Column::CreatedAt.gte(since));
+        }
+        if let Some(until) = filter.until {
+            query = query.filter(message::Column::CreatedAt.lt(until));
+        }
+        if let Some(after) = filter.after {
+            query = query.filter(message::Column::Id.gt(after));
+        }
+        if let Some(before) = filter.before {
+            query = query.filter(message::Column::Id.lt(before));
+        }
+
+        // Paging forward from `after` walks up the ids, anything else walks down from the newest
+        let ascending = filter.after.is_some() && filter.before.is_none();
+        query = if ascending {
+            query.order_by_asc(message::Column::Id)
+        } else {
+            query.order_by_desc(message::Column::Id)
+        };
+
+        // One extra row tells whether another page exists
+        let mut messages = query
+            .limit(filter.limit.map(|limit| limit + 1))
             .all(&self.conn)
-            .await
+            .await?;
+        let has_more = filter.limit.is_some_and(|limit| messages.len() as u64 > limit);
+        if has_more {
+            messages.pop();
+        }
+        if ascending {
+            messages.reverse();
+        }
+
+        let newest = messages.first().map(|m| m.id);
+        let oldest = messages.last().map(|m| m.id);
+        let (next_cursor, prev_cursor) = if ascending {
+            (oldest, if has_more { newest } else { None })
+        } else {
+            (if has_more { oldest } else { None }, filter.before.and(newest))
+        };
+
+        Ok(MessagePage { messages, next_cursor, prev_cursor })
+    }
+
+    /// Whether a message of this channel is visible.
+    pub async fn is_visible(&self, visibility: Visibility, channel_id: Option<i32>) -> Result<bool, DbErr> {
+        match (visibility, channel_id) {
+            (Visibility::All, _) | (_, None) => Ok(true),
+            (Visibility::Public, Some(_)) => Ok(false),
+            (Visibility::Member(user_id), Some(channel_id)) => self.is_channel_member(channel_id, user_id).await,
+        }
+    }
+
+    pub async fn get_message(&self, id: i32) -> Result<message::Model, DbErr> {
+        message::Entity::find_by_id(id)
+            .one(&self.conn)
+            .await?
+            .ok_or(DbErr::RecordNotFound(format!("Message with id {} not found", id)))
+    }
+
+    /// Update the given fields of a message, leaving the others untouched.
+    pub async fn update_message(&self, id: i32, text: Option<String>) -> Result<message::Model, DbErr> {
+        let mut msg: message::ActiveModel = self.get_message(id).await?.into();
+        if let Some(text) = text {
+            msg.text = Set(text);
+        }
+        msg.update(&self.conn).await
+    }
+
+    /// Delete a message and return it.
+    pub async fn delete_message(&self, id: i32) -> Result<message::Model, DbErr> {
+        let msg = self.get_message(id).await?;
+        let res = message::Entity::delete_by_id(id)
+            .exec(&self.conn)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(DbErr::RecordNotFound(format!("Message with id {} not found", id)));
+        }
+        Ok(msg)
+    }
+
+    /// Root of the thread of each given message, by message id. A message without parent is its own root.
+    pub async fn thread_root_ids(&self, ids: &[i32]) -> Result<HashMap<i32, i32>, DbErr> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let backend = self.conn.get_database_backend();
+        let placeholders = (1..=ids.len()).map(|i| format!("${}", i)).collect::<Vec<_>>().join(", ");
+        let sql = THREAD_ROOTS.replace("{ids}", &placeholders);
+        let rows = self.conn
+            .query_all(Statement::from_sql_and_values(backend, sql, ids.iter().map(|id| (*id).into())))
+            .await?;
+        rows.iter()
+            .map(|row| Ok((row.try_get("", "start_id")?, row.try_get("", "root_id")?)))
+            .collect()
+    }
+
+    pub async fn thread_root_id(&self, id: i32) -> Result<i32, DbErr> {
+        self.thread_root_ids(&[id]).await?
+            .remove(&id)
+            .ok_or(DbErr::RecordNotFound(format!("Message with id {} not found", id)))
     }
 
-    pub async fn get_messages_count(&self) -> Result<u64, DbErr> {
+    /// The thread starting at `root_id`, oldest first.
+    pub async fn get_thread(&self, root_id: i32) -> Result<Vec<message::Model>, DbErr> {
+        let backend = self.conn.get_database_backend();
         message::Entity::find()
-            .count(&self.conn)
+            .from_raw_sql(Statement::from_sql_and_values(backend, THREAD, [root_id.into()]))
+            .all(&self.conn)
             .await
     }
 
-    pub async fn get_users(&self) -> Result<Vec<user::Model>, DbErr> {
-        user::Entity::find()
+    /// Number of direct replies of each given message, messages without replies are left out.
+    pub async fn reply_counts(&self, ids: &[i32]) -> Result<HashMap<i32, u64>, DbErr> {
+        let counts = message::Entity::find()
+            .select_only()
+            .column(message::Column::ParentId)
+            .column_as(message::Column::Id.count(), "count")
+            .filter(message::Column::ParentId.is_in(ids.iter().copied()))
+            .group_by(message::Column::ParentId)
+            .into_tuple::<(i32, i64)>()
             .all(&self.conn)
-            .await
+            .await?;
+        Ok(counts.into_iter().map(|(id, count)| (id, count as u64)).collect())
+    }
+
+    /// Channel of each given message that exists, in a single query.
+    pub async fn get_message_channel_ids(&self, ids: &[i32]) -> Result<HashMap<i32, Option<i32>>, DbErr> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let pairs = message::Entity::find()
+            .select_only()
+            .column(message::Column::Id)
+            .column(message::Column::ChannelId)
+            .filter(message::Column::Id.is_in(ids.iter().copied()))
+            .into_tuple::<(i32, Option<i32>)>()
+            .all(&self.conn)
+            .await?;
+        Ok(pairs.into_iter().collect())
     }
 
+    /// Authors of the given messages, in the same order.
+    pub async fn get_message_authors(&self, messages: &[message::Model]) -> Result<Vec<Option<user::Model>>, DbErr> {
+        messages.load_one(user::Entity, &self.conn).await
+    }
+
+    /// Ranked full-text search over the message text, every word of `query` must match.
+    pub async fn search_messages(&self, query: &str, visibility: Visibility, limit: u64, offset: u64) -> Result<Vec<SearchHit>, DbErr> {
+        let backend = self.conn.get_database_backend();
+        if backend != DbBackend::Sqlite {
+            return Err(DbErr::Custom("full-text search needs SQLite".to_string()));
+        }
+
+        let sql = SQLITE_SEARCH.replace("{visible}", visibility.sql());
+        let mut values: Vec<Value> = vec![fts5_query(query).into(), limit.into(), offset.into()];
+        if let Visibility::Member(user_id) = visibility {
+            values.push(user_id.into());
+        }
+        let rows = self.conn
+            .query_all(Statement::from_sql_and_values(backend, sql, values))
+            .await?;
+        rows.iter()
+            .map(|row| Ok(SearchHit {
+                message: message::Model::from_query_result(row, "")?,
+                snippet: highlight(&row.try_get::<String>("", "snippet")?),
+                score: row.try_get("", "score")?,
+            }))
+            .collect()
+    }
+
+    pub async fn get_messages_count(&self, visibility: Visibility) -> Result<u64, DbErr> {
+        let mut query = message::Entity::find();
+        if let Some(condition) = visibility.condition() {
+            query = query.filter(condition);
+        }
+        query.count(&self.conn).await
+    }
+
+    pub async fn get_users(&self, filter: &UserFilter) -> Result<UserPage, DbErr> {
+        let mut query = user::Entity::find();
+        if let Some(search) = &filter.search {
+            query = query.filter(Condition::any()
+                .add(user::Column::Name.contains(search))
+                .add(user::Column::Email.contains(search)));
+        }
+        if let Some(since) = filter.since {
+            query = query.filter(user::Column::CreatedAt.gte(since));
+        }
+        if let Some(until) = filter.until {
+            query = query.filter(user::Column::CreatedAt.lt(until));
+        }
+        if let Some(after) = filter.after {
+            query = query.filter(user::Column::Id.gt(after));
+        }
+
+        // One extra row tells whether another page exists
+        let mut users = query
+            .order_by_asc(user::Column::Id)
+            .limit(filter.limit.map(|limit| limit + 1))
+            .all(&self.conn)
+            .await?;
+        let has_more = filter.limit.is_some_and(|limit| users.len() as u64 > limit);
+        if has_more {
+            users.pop();
+        }
+        let next_cursor = if has_more { users.last().map(|u| u.id) } else { None };
+
+        Ok(UserPage { users, next_cursor })
+    }
+
+
     pub async fn get_user(&self, user_id: i32) -> Result<user::Model, DbErr> {
         user::Entity::find()
             .filter(user::Column::Id.eq(user_id))
@@ -50,4 +466,155 @@ impl WamDatabase {
             .await?
             .ok_or(DbErr::RecordNotFound(format!("User with id {} not found", user_id)))
     }
-}
\ No newline at end of file
+
+    pub async fn get_user_by_email(&self, email: &str) -> Result<Option<user::Model>, DbErr> {
+        user::Entity::find()
+            .filter(user::Column::Email.eq(email))
+            .one(&self.conn)
+            .await
+    }
+
+    /// Change the given fields of a user, `None` leaves a field untouched.
+    pub async fn update_user(&self, user_id: i32, name: Option<String>, email: Option<String>, password_hash: Option<String>) -> Result<user::Model, DbErr> {
+        let mut user: user::ActiveModel = self.get_user(user_id).await?.into();
+        if let Some(name) = name {
+            user.name = Set(name);
+        }
+        if let Some(email) = email {
+            user.email = Set(email);
+        }
+        if let Some(password_hash) = password_hash {
+            // A new password ends the sessions opened with the old one
+            user.token_version = Set(user.token_version.as_ref() + 1);
+            user.password_hash = Set(Some(password_hash));
+        }
+        user.update(&self.conn).await
+    }
+
+    /// Revoke every token issued to the user so far.
+    pub async fn revoke_tokens(&self, user_id: i32) -> Result<user::Model, DbErr> {
+        let mut user: user::ActiveModel = self.get_user(user_id).await?.into();
+        user.token_version = Set(user.token_version.as_ref() + 1);
+        user.update(&self.conn).await
+    }
+
+    pub async fn set_user_role(&self, user_id: i32, role: user::Role) -> Result<user::Model, DbErr> {
+        let mut user: user::ActiveModel = self.get_user(user_id).await?.into();
+        // Sessions opened under the old role are ended
+        user.token_version = Set(user.token_version.as_ref() + 1);
+        user.role = Set(role);
+        user.update(&self.conn).await
+    }
+
+    pub async fn count_users_with_role(&self, role: user::Role) -> Result<u64, DbErr> {
+        user::Entity::find()
+            .filter(user::Column::Role.eq(role))
+            .count(&self.conn)
+            .await
+    }
+
+    pub async fn count_user_messages(&self, user_id: i32) -> Result<u64, DbErr> {
+        message::Entity::find()
+            .filter(message::Column::UserId.eq(user_id))
+            .count(&self.conn)
+            .await
+    }
+
+    /// Delete a user, first deleting their messages or handing them to the tombstone user as
+    /// `policy` says. Under `OnUserDelete::Restrict` the foreign key refuses users that still have messages.
+    pub async fn delete_user(&self, user_id: i32, policy: OnUserDelete) -> Result<DeletedUser, DbErr> {
+        let user = self.get_user(user_id).await?;
+        let txn = self.conn.begin().await?;
+
+        let mut reassigned_to = None;
+        let messages = match policy {
+            OnUserDelete::Restrict => 0,
+            OnUserDelete::Cascade => {
+                message::Entity::delete_many()
+                    .filter(message::Column::UserId.eq(user_id))
+                    .exec(&txn)
+                    .await?
+                    .rows_affected
+            }
+            OnUserDelete::Tombstone => {
+                let tombstone = tombstone_user(&txn).await?;
+                reassigned_to = Some(tombstone.id);
+                message::Entity::update_many()
+                    .col_expr(message::Column::UserId, sea_query::Expr::value(tombstone.id))
+                    .filter(message::Column::UserId.eq(user_id))
+                    .exec(&txn)
+                    .await?
+                    .rows_affected
+            }
+        };
+
+        let res = user::Entity::delete_by_id(user_id).exec(&txn).await?;
+        if res.rows_affected == 0 {
+            return Err(DbErr::RecordNotFound(format!("User with id {} not found", user_id)));
+        }
+        txn.commit().await?;
+
+        Ok(DeletedUser { user, messages, reassigned_to })
+    }
+}
+
+/// The user owning the messages of deleted users, created the first time it is needed.
+async fn tombstone_user<C: ConnectionTrait>(conn: &C) -> Result<user::Model, DbErr> {
+    let existing = user::Entity::find()
+        .filter(user::Column::Tombstone.eq(true))
+        .one(conn)
+        .await?;
+    match existing {
+        Some(user) => Ok(user),
+        None => {
+            user::ActiveModel {
+                name: Set(TOMBSTONE_NAME.to_string()),
+                email: Set(TOMBSTONE_EMAIL.to_string()),
+                role: Set(user::Role::ReadOnly),
+                tombstone: Set(true),
+                ..Default::default()
+            }
+            .insert(conn)
+            .await
+        }
+    }
+}
+
+fn new_message(msg: &message::Model) -> message::ActiveModel {
+    message::ActiveModel{
+        text: Set(msg.text.clone()),
+        user_id: Set(msg.user_id),
+        client_msg_id: Set(msg.client_msg_id.clone()),
+        parent_id: Set(msg.parent_id),
+        channel_id: Set(msg.channel_id),
+        source: Set(msg.source),
+        ..Default::default()
+    }
+}
+
+/// Escape the snippet text, which is written by users, and only then turn the match delimiters
+/// chosen in `SQLITE_SEARCH` into `<mark>` tags.
+fn highlight(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len());
+    for c in snippet.chars() {
+        match c {
+            '\u{2}' => html.push_str("<mark>"),
+            '\u{3}' => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+/// Quote every word so FTS5 operators typed by users are searched as plain text.
+fn fts5_query(query: &str) -> String {
+    query.split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use sea_orm::*;
+use ::entity::message as message;
+
+use crate::database::requests::Visibility;
+use crate::database::WamDatabase;
+
+/// Messages per bucket, a bucket being the seconds since the epoch divided by the bucket width
+const SQLITE_RATES: &str = r#"
+SELECT CAST(strftime('%s', created_at) AS INTEGER) / $1 AS bucket, COUNT(*) AS count
+FROM message
+WHERE created_at >= $2 AND created_at < $3 {visible}
+GROUP BY bucket
+"#;
+
+impl WamDatabase {
+    /// Number of visible messages of the `limit` users who posted the most, most active first.
+    pub async fn get_user_message_counts(&self, visibility: Visibility, limit: u64) -> Result<Vec<(i32, i64)>, DbErr> {
+        let mut query = message::Entity::find()
+            .select_only()
+            .column(message::Column::UserId)
+            .column_as(message::Column::Id.count(), "count");
+        if let Some(condition) = visibility.condition() {
+            query = query.filter(condition);
+        }
+        query
+            .group_by(message::Column::UserId)
+            .order_by_desc(message::Column::Id.count())
+            .order_by_asc(message::Column::UserId)
+            .limit(limit)
+            .into_tuple::<(i32, i64)>()
+            .all(&self.conn)
+            .await
+    }
+
+    /// Number of visible messages stored through each source, sources without messages are left out.
+    pub async fn get_message_source_counts(&self, visibility: Visibility) -> Result<HashMap<message::Source, u64>, DbErr> {
+        let mut query = message::Entity::find()
+            .select_only()
+            .column(message::Column::Source)
+            .column_as(message::Column::Id.count(), "count")
+            .filter(message::Column::Source.is_not_null());
+        if let Some(condition) = visibility.condition() {
+            query = query.filter(condition);
+        }
+        let counts = query
+            .group_by(message::Column::Source)
+            .into_tuple::<(message::Source, i64)>()
+            .all(&self.conn)
+            .await?;
+        Ok(counts.into_iter().map(|(source, count)| (source, count as u64)).collect())
+    }
+
+    /// Visible messages created in `[since, until)`, counted in buckets of `width_secs` seconds.
+    /// Buckets are keyed by their index since the epoch, empty ones are left out.
+    pub async fn get_message_rates(&self, visibility: Visibility, since: DateTime<Utc>, until: DateTime<Utc>, width_secs: i64) -> Result<HashMap<i64, i64>, DbErr> {
+        let backend = self.conn.get_database_backend();
+        if backend != DbBackend::Sqlite {
+            return Err(DbErr::Custom("message rates need SQLite".to_string()));
+        }
+
+        let sql = SQLITE_RATES.replace("{visible}", visibility.sql());
+        let mut values: Vec<Value> = vec![width_secs.into(), since.into(), until.into()];
+        if let Visibility::Member(user_id) = visibility {
+            values.push(user_id.into());
+        }
+        let rows = self.conn
+            .query_all(Statement::from_sql_and_values(backend, sql, values))
+            .await?;
+        rows.iter()
+            .map(|row| Ok((row.try_get::<i64>("", "bucket")?, row.try_get::<i64>("", "count")?)))
+            .collect()
+    }
+}
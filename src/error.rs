@@ -0,0 +1,226 @@
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use log::{error, warn};
+use sea_orm::{DbErr, SqlErr};
+use serde::Serialize;
+use validator::{ValidationError, ValidationErrors};
+
+/// Crate-wide error returned by every API route.
+#[derive(Debug)]
+pub enum WamError {
+    /// The requested resource (or one it references) does not exist
+    NotFound(String),
+    /// The request clashes with existing data, e.g. a duplicate email
+    Conflict(String),
+    /// The request lacks valid credentials
+    Unauthorized(String),
+    /// The caller may not do this, e.g. post in a channel they have not joined
+    Forbidden(String),
+    /// The request body cannot be parsed at all, e.g. broken JSON
+    Malformed(String),
+    /// The body is not sent with a content type the route reads, e.g. JSON without `application/json`
+    UnsupportedMediaType(String),
+    /// The request is malformed or its content is invalid
+    Validation(String),
+    /// Some fields break their validation rules
+    InvalidFields(Vec<FieldError>),
+    /// The feature is not available with this setup, e.g. search on a database other than SQLite
+    NotImplemented(String),
+    /// Unexpected database failure
+    Db(DbErr),
+    /// Unexpected failure of the server itself, e.g. hashing a password
+    Internal(String),
+}
+
+/// RFC 7807 problem details body, with a stable `code` extension member.
+#[derive(Debug, Serialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: &'static str,
+    /// One entry per invalid field, for `validation_failed` errors
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+/// A broken validation rule of a request field.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    /// Name of the rule, e.g. `length` or `email`
+    pub code: String,
+    pub message: String,
+}
+
+impl WamError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            WamError::NotFound(_) => StatusCode::NOT_FOUND,
+            WamError::Conflict(_) => StatusCode::CONFLICT,
+            WamError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            WamError::Forbidden(_) => StatusCode::FORBIDDEN,
+            WamError::Malformed(_) => StatusCode::BAD_REQUEST,
+            WamError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            WamError::Validation(_) | WamError::InvalidFields(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            WamError::NotImplemented(_) => StatusCode::NOT_IMPLEMENTED,
+            WamError::Db(DbErr::Conn(_) | DbErr::ConnectionAcquire(_)) => StatusCode::SERVICE_UNAVAILABLE,
+            WamError::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            WamError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Machine readable error code, stable across releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            WamError::NotFound(_) => "not_found",
+            WamError::Conflict(_) => "conflict",
+            WamError::Unauthorized(_) => "unauthorized",
+            WamError::Forbidden(_) => "forbidden",
+            WamError::Malformed(_) => "malformed_request",
+            WamError::UnsupportedMediaType(_) => "unsupported_media_type",
+            WamError::Validation(_) | WamError::InvalidFields(_) => "validation_failed",
+            WamError::NotImplemented(_) => "not_implemented",
+            WamError::Db(DbErr::Conn(_) | DbErr::ConnectionAcquire(_)) => "database_unavailable",
+            WamError::Db(_) => "database_error",
+            WamError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn detail(&self) -> String {
+        match self {
+            WamError::NotFound(detail)
+            | WamError::Conflict(detail)
+            | WamError::Unauthorized(detail)
+            | WamError::Forbidden(detail)
+            | WamError::Malformed(detail)
+            | WamError::UnsupportedMediaType(detail)
+            | WamError::Validation(detail)
+            | WamError::NotImplemented(detail) => detail.clone(),
+            WamError::InvalidFields(errors) => errors.iter()
+                .map(|e| format!("{} {}", e.field, e.message))
+                .collect::<Vec<_>>()
+                .join(", "),
+            // Database and server internals are logged, not sent to clients
+            WamError::Db(_) => "A database error occurred".to_string(),
+            WamError::Internal(_) => "An internal error occurred".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for WamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WamError::Db(e) => write!(f, "{}: {}", self.code(), e),
+            WamError::Internal(detail) => write!(f, "{}: {}", self.code(), detail),
+            _ => write!(f, "{}: {}", self.code(), self.detail()),
+        }
+    }
+}
+
+impl std::error::Error for WamError {}
+
+impl From<DbErr> for WamError {
+    fn from(e: DbErr) -> Self {
+        // The driver text names tables and columns, routes knowing the field give their own message
+        match e.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(detail)) => {
+                warn!("Unique constraint violation: {}", detail);
+                WamError::Conflict("the request conflicts with existing data".to_string())
+            }
+            Some(SqlErr::ForeignKeyConstraintViolation(detail)) => {
+                warn!("Foreign key constraint violation: {}", detail);
+                WamError::Conflict("the request references missing data or data still in use".to_string())
+            }
+            _ => match e {
+                DbErr::RecordNotFound(detail) => WamError::NotFound(detail),
+                e => WamError::Db(e),
+            },
+        }
+    }
+}
+
+impl From<ValidationErrors> for WamError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut fields: Vec<_> = errors.field_errors().into_iter().collect();
+        fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+        WamError::InvalidFields(fields.into_iter()
+            .flat_map(|(field, errors)| errors.iter().map(move |e| FieldError {
+                field: field.to_string(),
+                code: e.code.to_string(),
+                message: rule_message(e),
+            }))
+            .collect())
+    }
+}
+
+/// Human readable description of a broken rule, from its parameters when it has no message.
+fn rule_message(error: &ValidationError) -> String {
+    if let Some(message) = &error.message {
+        return message.to_string();
+    }
+    let param = |name: &str| error.params.get(name).map(|value| value.to_string());
+    match error.code.as_ref() {
+        "length" => match (param("min"), param("max")) {
+            (Some(min), Some(max)) => format!("must be {} to {} characters long", min, max),
+            (Some(min), None) => format!("must be at least {} characters long", min),
+            (None, Some(max)) => format!("must be at most {} characters long", max),
+            (None, None) => "has an invalid length".to_string(),
+        },
+        "email" => "must be a valid email address".to_string(),
+        code => format!("breaks the {} rule", code),
+    }
+}
+
+impl From<JsonRejection> for WamError {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection {
+            JsonRejection::JsonSyntaxError(_) => WamError::Malformed(rejection.body_text()),
+            JsonRejection::MissingJsonContentType(_) => WamError::UnsupportedMediaType(rejection.body_text()),
+            _ => WamError::Validation(rejection.body_text()),
+        }
+    }
+}
+
+impl From<PathRejection> for WamError {
+    fn from(rejection: PathRejection) -> Self {
+        WamError::Validation(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for WamError {
+    fn from(rejection: QueryRejection) -> Self {
+        WamError::Validation(rejection.body_text())
+    }
+}
+
+impl IntoResponse for WamError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            error!("{}", self);
+        }
+
+        let body = ProblemDetails {
+            problem_type: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: self.detail(),
+            code: self.code(),
+            errors: match self {
+                WamError::InvalidFields(errors) => errors,
+                _ => Vec::new(),
+            },
+        };
+
+        let mut response = (status, [(header::CONTENT_TYPE, "application/problem+json")], Json(body)).into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static("Bearer"));
+        }
+        response
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Last known state of a background loop, updated by the loop itself.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TaskStatus {
+    pub last_attempt: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// Shared health information of the Kafka consumer and SYTRAL poller.
+#[derive(Debug, Default)]
+pub struct HealthState {
+    kafka: Mutex<TaskStatus>,
+    sytral: Mutex<TaskStatus>,
+}
+
+impl TaskStatus {
+    fn record(&mut self, result: Result<(), String>) {
+        let now = Utc::now();
+        self.last_attempt = Some(now);
+        match result {
+            Ok(()) => {
+                self.last_success = Some(now);
+                self.last_error = None;
+            }
+            Err(e) => self.last_error = Some(e),
+        }
+    }
+
+    /// A loop is stale when it has not succeeded within `max_age_secs`.
+    pub fn is_stale(&self, max_age_secs: u64) -> bool {
+        match self.last_success {
+            Some(at) => (Utc::now() - at).num_seconds() > max_age_secs as i64,
+            None => true,
+        }
+    }
+}
+
+impl HealthState {
+    pub fn record_kafka_poll(&self, result: Result<(), String>) {
+        self.kafka.lock().unwrap().record(result);
+    }
+
+    pub fn record_sytral_fetch(&self, result: Result<(), String>) {
+        self.sytral.lock().unwrap().record(result);
+    }
+
+    pub fn kafka(&self) -> TaskStatus {
+        self.kafka.lock().unwrap().clone()
+    }
+
+    pub fn sytral(&self) -> TaskStatus {
+        self.sytral.lock().unwrap().clone()
+    }
+}
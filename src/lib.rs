@@ -0,0 +1,39 @@
+//! wamserver: message, user and vehicle APIs over HTTP and WebSocket.
+//!
+//! Embed it by building a state and mounting the router:
+//!
+//! ```no_run
+//! # async fn example(db: wamserver::database::WamDatabase) -> Result<(), wamserver::WamError> {
+//! use wamserver::{build_app, BackgroundTasks, WamServerState};
+//!
+//! let state = WamServerState::builder()
+//!     .database(db)
+//!     .broadcast_capacity(1000)
+//!     .background_tasks(BackgroundTasks::none())
+//!     .build()
+//!     .await?;
+//! let app: axum::Router = build_app(state);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod app;
+pub mod auth;
+pub mod cli;
+pub mod commands;
+pub mod config;
+pub mod routes;
+pub mod database;
+pub mod error;
+pub mod health;
+pub mod messaging;
+pub mod metrics;
+pub mod shutdown;
+pub mod state;
+pub mod stats;
+#[cfg(feature = "tls")]
+pub mod tls;
+
+pub use app::{build_admin_router, build_api_router, build_app, build_listener_app};
+pub use error::WamError;
+pub use state::{BackgroundTasks, WamServerState, WamServerStateBuilder};
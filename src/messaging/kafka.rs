@@ -1,69 +1,158 @@
 use kafka::consumer::{Consumer, FetchOffset};
-use std::env;
 use log::{info, error};
+use sea_orm::SqlErr;
+use validator::Validate;
+use entity::message::Source;
 
-use crate::{messaging::websocket::broadcast_message, WamServerState};
+use crate::error::WamError;
+use crate::messaging::placement::Placement;
+use crate::messaging::websocket::{broadcast_to_channel, NewMessage};
+use crate::{metrics, WamServerState};
 
 pub async fn consume_kafka_message(state: WamServerState) {
 
-    loop {
+    let config = state.config.kafka.clone();
+
+    while !state.shutdown.is_cancelled() {
         // Create Kafka consumer
-        let host = env::var("KAFKA_URL").expect("KAFKA_URL must be set");
-        let topic: String = env::var("KAFKA_TOPIC").expect("KAFKA_TOPIC must be set");
-        let group: String = env::var("KAFKA_GROUP").expect("KAFKA_GROUP must be set");
-        info!("Executing Kafka consuming loop: host={}, topic={}, group={}", host, topic, group);
+        info!("Executing Kafka consuming loop: host={}, topic={}, group={}", config.url, config.topic, config.group);
             
-        let consumer_res=
-        Consumer::from_hosts(vec!(host.to_owned()))
-            .with_topic(topic.to_owned())
+        let mut builder = Consumer::from_hosts(vec!(config.url.to_owned()))
+            .with_topic(config.topic.to_owned());
+        for topic in config.channel_topics.keys() {
+            builder = builder.with_topic(topic.to_owned());
+        }
+        let consumer_res = builder
             .with_fallback_offset(FetchOffset::Earliest)
-            .with_group(group)
+            .with_group(config.group.to_owned())
             .with_offset_storage(Some(kafka::consumer::GroupOffsetStorage::Kafka))
             .create();
 
-        let _ = match consumer_res {
+        match consumer_res {
             Ok(mut c) => {
-                for ms in c.poll().unwrap().iter() {
+                let message_sets = match c.poll() {
+                    Ok(message_sets) => {
+                        state.health.record_kafka_poll(Ok(()));
+                        message_sets
+                    }
+                    Err(e) => {
+                        error!("Error polling Kafka: {}", e);
+                        state.health.record_kafka_poll(Err(e.to_string()));
+                        wait_next_poll(&state, config.poll_interval_secs).await;
+                        continue;
+                    }
+                };
+
+                for ms in message_sets.iter() {
                     for m in ms.messages() {
                     let str = String::from_utf8_lossy(m.value);
-                    println!("Consuming message from Kafka \"topic\" {} : {:?}",topic, str);
+                    println!("Consuming message from Kafka \"topic\" {} : {:?}", ms.topic(), str);
 
                     // Create message from string 
-                    let message = serde_json::from_str::<entity::message::Model>(&str)
-                        .map_err(|e| {
-                            println!("Error parsing message: {}", e);
-                        });
-
-                        if message.is_ok(){
-                            let ok_msg = message.as_ref().unwrap();
-                            // Save message to database
-                            let res = state.db.create_message(ok_msg).await;
-                            
-                            if res.is_err() {
-                                error!("Error saving message to database: {:?}", res.err());
-                                continue;
-                            }
+                    let message = serde_json::from_str::<entity::message::Model>(&str);
+
+                        match message {
+                            Ok(mut ok_msg) => {
+                                ok_msg.client_msg_id = ok_msg.client_msg_id.filter(|id| !id.is_empty());
+                                ok_msg.source = Some(Source::Kafka);
+                                if let Err(errors) = ok_msg.validate() {
+                                    error!("Dropping invalid Kafka message: {}", WamError::from(errors));
+                                    continue;
+                                }
+
+                                // Channel topics post in their channel, unless the message names one
+                                if ok_msg.channel_id.is_none()
+                                    && let Some(name) = config.channel_topics.get(ms.topic())
+                                {
+                                    match state.db.get_channel_by_name(name).await {
+                                        Ok(Some(channel)) => ok_msg.channel_id = Some(channel.id),
+                                        Ok(None) => {
+                                            error!("Dropping Kafka message for unknown channel {}", name);
+                                            continue;
+                                        }
+                                        Err(e) => {
+                                            error!("Error loading channel {}: {:?}", name, e);
+                                            continue;
+                                        }
+                                    }
+                                }
+
+                                let placed = match Placement::load(&state.db, [&ok_msg]).await {
+                                    Ok(placement) => placement.place(&mut ok_msg),
+                                    Err(e) => Err(e.into()),
+                                };
+                                if let Err(e) = placed {
+                                    error!("Dropping Kafka message from user {}: {}", ok_msg.user_id, e);
+                                    continue;
+                                }
 
-                            // Push message to web socket clients
-                            broadcast_message(&state.ws_sender, "message".to_string(), res.unwrap())
-                                .unwrap_or_else(|e| {
-                                    error!("Error broadcasting message to WebSocket clients: {}", e);
-                                });
+                                let root_id = match ok_msg.parent_id {
+                                    Some(parent_id) => match state.db.thread_root_id(parent_id).await {
+                                        Ok(root_id) => Some(root_id),
+                                        Err(e) => {
+                                            error!("Dropping Kafka reply to message {}: {}", parent_id, e);
+                                            continue;
+                                        }
+                                    },
+                                    None => None,
+                                };
 
-                        } else {
-                            error!("Error parsing message from Kafka: {:?}", message.err());
-                            continue;
+                                // Save message to database
+                                let res = state.db.create_message(&ok_msg).await;
+
+                                let saved = match res {
+                                    // Redelivered message, it was already stored and broadcast
+                                    Ok(stored) if !stored.created => {
+                                        info!("Skipping already stored Kafka message {}", stored.message.id);
+                                        continue;
+                                    }
+                                    Ok(stored) => {
+                                        state.metrics.message_created(metrics::SOURCE_KAFKA);
+                                        stored.message
+                                    }
+                                    Err(e) if matches!(e.sql_err(), Some(SqlErr::ForeignKeyConstraintViolation(_))) => {
+                                        error!("Dropping Kafka message for unknown user {}", ok_msg.user_id);
+                                        continue;
+                                    }
+                                    Err(e) => {
+                                        error!("Error saving message to database: {:?}", e);
+                                        continue;
+                                    }
+                                };
+
+                                // Push message to web socket clients
+                                broadcast_to_channel(&state, saved.channel_id, "message".to_string(), NewMessage { message: &saved, root_id });
+                            }
+                            Err(e) => {
+                                error!("Error parsing message from Kafka: {}", e);
+                                state.metrics.kafka_parse_failures.inc();
+                                continue;
+                            }
                         }
                     }
                     let _ = c.consume_messageset(ms);
                 }
-                c.commit_consumed().unwrap();
+                // Offsets are committed for the whole batch, including on shutdown
+                if let Err(e) = c.commit_consumed() {
+                    error!("Error committing Kafka offsets: {}", e);
+                }
             },
             Err(e) => {
                 error!("Error creating Kafka consumer: {}", e);
+                state.health.record_kafka_poll(Err(e.to_string()));
             }
         };
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        wait_next_poll(&state, config.poll_interval_secs).await;
    }
+
+    info!("Kafka consumer stopped");
+}
+
+/// Sleep until the next poll, returning early when the server shuts down.
+async fn wait_next_poll(state: &WamServerState, secs: u64) {
+    tokio::select! {
+        _ = state.shutdown.cancelled() => {},
+        _ = tokio::time::sleep(tokio::time::Duration::from_secs(secs)) => {},
+    }
 }
\ No newline at end of file
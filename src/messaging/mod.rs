@@ -1,3 +1,6 @@
+#[cfg(feature = "kafka")]
 pub mod kafka;
+pub mod placement;
 pub mod websocket;
+#[cfg(feature = "sytral")]
 pub mod sytral;
@@ -0,0 +1,65 @@
+use std::collections::{HashMap, HashSet};
+
+use sea_orm::DbErr;
+
+use crate::database::WamDatabase;
+use crate::error::WamError;
+
+/// Where new messages may go: the thread they reply to and the channel they are posted in.
+/// Everything is loaded up front in a few queries, whatever the number of messages.
+#[derive(Debug, Default)]
+pub struct Placement {
+    /// Channel of each parent message that exists
+    parents: HashMap<i32, Option<i32>>,
+    channels: HashSet<i32>,
+    /// `(channel_id, user_id)` pairs
+    memberships: HashSet<(i32, i32)>,
+}
+
+impl Placement {
+    pub async fn load<'a>(db: &WamDatabase, messages: impl IntoIterator<Item = &'a entity::message::Model>) -> Result<Self, DbErr> {
+        let messages: Vec<_> = messages.into_iter().collect();
+        let parent_ids: Vec<i32> = messages.iter().filter_map(|m| m.parent_id).collect();
+        let parents = db.get_message_channel_ids(&parent_ids).await?;
+
+        // Replies may inherit the channel of their parent
+        let channel_ids: Vec<i32> = messages.iter()
+            .filter_map(|m| m.channel_id)
+            .chain(parents.values().flatten().copied())
+            .collect();
+        if channel_ids.is_empty() {
+            return Ok(Placement { parents, ..Default::default() });
+        }
+
+        Ok(Placement {
+            parents,
+            channels: db.existing_channel_ids(&channel_ids).await?,
+            memberships: db.get_memberships(&channel_ids).await?,
+        })
+    }
+
+    /// Check that the message can be posted, a reply without channel joins the one of its parent.
+    pub fn place(&self, message: &mut entity::message::Model) -> Result<(), WamError> {
+        if let Some(parent_id) = message.parent_id {
+            let parent_channel = *self.parents.get(&parent_id)
+                .ok_or_else(|| WamError::NotFound(format!("Parent message with id {} not found", parent_id)))?;
+            match message.channel_id {
+                None => message.channel_id = parent_channel,
+                channel_id if channel_id != parent_channel => {
+                    return Err(WamError::Validation("a reply must be posted in the channel of the message it replies to".to_string()));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(channel_id) = message.channel_id {
+            if !self.channels.contains(&channel_id) {
+                return Err(WamError::NotFound(format!("Channel with id {} not found", channel_id)));
+            }
+            if !self.memberships.contains(&(channel_id, message.user_id)) {
+                return Err(WamError::Forbidden(format!("User {} is not a member of channel {}", message.user_id, channel_id)));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,22 +1,23 @@
-use std::env;
-
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
 use log::{info, error};
 use crate::messaging::websocket::{broadcast_message};
 use chrono::{DateTime, Utc};
+#[cfg(feature = "kafka")]
 use kafka::producer::{Producer, Record, RequiredAcks};
+#[cfg(feature = "kafka")]
 use prost::Message;
 use anyhow::Result;
 
+#[cfg(feature = "kafka")]
+use crate::config::KafkaConfig;
+use crate::config::SytralConfig;
+
 // Include the generated protobuf code
 pub mod proto {
     include!(concat!(env!("OUT_DIR"), "/sytral.rs"));
 }
 
-const SYTRAL_URL: &str = "https://data.grandlyon.com/siri-lite/2.0/vehicle-monitoring.json";
-
-
 /// High-level struct returned to the caller.
 /// Clean and easy to work with.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -98,13 +99,11 @@ struct VehicleLocation {
 }
 
 /// Public API: fetch all real-time vehicles from SYTRAL SIRI-Lite JSON
-async fn get_vehicles() -> Result<VehicleList> {
+async fn get_vehicles(config: &SytralConfig) -> Result<VehicleList> {
     let client = Client::new();
-    let username = env::var("SYTRAL_USERNAME").expect("SYTRAL_USERNAME must be set");
-    let password = env::var("SYTRAL_PASSWORD").expect("SYTRAL_PASSWORD must be set");
         
-    let response = client.get(SYTRAL_URL)
-    .basic_auth(&username, Some(&password))
+    let response = client.get(&config.url)
+    .basic_auth(&config.username, Some(&config.password))
     .send().await?;
     
     info!("Fetched SYTRAL data with status: {}", response.status());
@@ -116,6 +115,20 @@ async fn get_vehicles() -> Result<VehicleList> {
     //info!("SYTRAL response snippet: {}", snippet);
     //let siri_root: SiriRoot = serde_json::from_str(&text)?;
 
+    Ok(vehicles_from_siri(siri_root))
+}
+
+/// Parse one line of a recorded capture: either a `VehicleList` or a raw SIRI-Lite response
+pub fn parse_capture(line: &str) -> Result<VehicleList> {
+    if let Ok(vehicles) = serde_json::from_str::<VehicleList>(line) {
+        return Ok(vehicles);
+    }
+    let siri_root: SiriRoot = serde_json::from_str(line)?;
+    Ok(vehicles_from_siri(siri_root))
+}
+
+/// Keep only the vehicles that report a location
+fn vehicles_from_siri(siri_root: SiriRoot) -> VehicleList {
     let mut vehicles = Vec::new();
 
     for delivery in siri_root.siri.service_delivery.vehicle_monitoring_delivery {
@@ -129,16 +142,17 @@ async fn get_vehicles() -> Result<VehicleList> {
                     direction: mvj.direction_ref.map(|w| w.value),
                     latitude: loc.latitude,
                     longitude: loc.longitude,
-                    timestamp: mvj.timestamp.unwrap_or_else(|| Utc::now()),
+                    timestamp: mvj.timestamp.unwrap_or_else(Utc::now),
                 });
             }
         }
     }
 
-    Ok(VehicleList { vehicles })
+    VehicleList { vehicles }
 }
 
 /// Convert VehicleList to protobuf format
+#[cfg(feature = "kafka")]
 fn to_proto_vehicle_list(vehicle_list: &VehicleList) -> proto::VehicleList {
     proto::VehicleList {
         vehicles: vehicle_list.vehicles.iter().map(|v| proto::Vehicle {
@@ -153,9 +167,9 @@ fn to_proto_vehicle_list(vehicle_list: &VehicleList) -> proto::VehicleList {
 }
 
 /// Send VehicleList to Kafka using protobuf encoding
-async fn send_to_kafka(vehicle_list: &VehicleList) -> Result<()> {
-    let kafka_url = env::var("KAFKA_URL").expect("KAFKA_URL must be set");
-    let topic = "vehicles";
+#[cfg(feature = "kafka")]
+async fn send_to_kafka(config: &KafkaConfig, vehicle_list: &VehicleList) -> Result<()> {
+    let topic = config.vehicles_topic.as_str();
     
     // Convert to protobuf
     let proto_vehicles = to_proto_vehicle_list(vehicle_list);
@@ -165,7 +179,7 @@ async fn send_to_kafka(vehicle_list: &VehicleList) -> Result<()> {
     proto_vehicles.encode(&mut buf)?;
     
     // Create Kafka producer
-    let mut producer = Producer::from_hosts(vec![kafka_url])
+    let mut producer = Producer::from_hosts(vec![config.url.clone()])
         .with_ack_timeout(std::time::Duration::from_secs(1))
         .with_required_acks(RequiredAcks::One)
         .create()?;
@@ -178,32 +192,48 @@ async fn send_to_kafka(vehicle_list: &VehicleList) -> Result<()> {
     Ok(())
 }
 
-pub async fn sytral_handler(state: crate::WamServerState) -> () {
-    loop {
+pub async fn sytral_handler(state: crate::WamServerState) {
+    let config = &state.config;
+
+    // The in-flight fetch always completes; shutdown is only checked between iterations
+    while !state.shutdown.is_cancelled() {
         info!("Executing Sytral consuming loop");
 
-        match get_vehicles().await {
+        let timer = state.metrics.sytral_fetch_duration.start_timer();
+        let fetched = get_vehicles(&config.sytral).await;
+        timer.observe_duration();
+
+        match fetched {
             Ok(vehicles) => {
                 info!("Fetched {} vehicles from SYTRAL", vehicles.vehicles.len());
+                state.metrics.sytral_vehicles.set(vehicles.vehicles.len() as i64);
+                state.health.record_sytral_fetch(Ok(()));
 
                 // Send to Kafka
-                if let Err(e) = send_to_kafka(&vehicles).await {
+                #[cfg(feature = "kafka")]
+                if config.kafka.enabled
+                    && let Err(e) = send_to_kafka(&config.kafka, &vehicles).await
+                {
                     error!("Error sending vehicles to Kafka: {}", e);
+                    state.metrics.kafka_send_failures.inc();
                 }
 
                 // Broadcast message to WebSocket clients
-                broadcast_message(&state.ws_sender, "sytral".to_string(), vehicles)
-                    .unwrap_or_else(|e| {
-                        error!("Error broadcasting message to WebSocket clients: {}", e);
-                    });
+                broadcast_message(&state, "sytral".to_string(), vehicles);
 
                 
             }
             Err(e) => {
                 info!("Error fetching vehicles from SYTRAL: {}", e);
+                state.health.record_sytral_fetch(Err(e.to_string()));
             }
         }
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        tokio::select! {
+            _ = state.shutdown.cancelled() => {},
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(config.sytral.poll_interval_secs)) => {},
+        }
     }
+
+    info!("SYTRAL poller stopped");
 }
\ No newline at end of file
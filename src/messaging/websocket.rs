@@ -3,14 +3,34 @@ use axum::extract::ws::Message;
 use log::error;
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
-use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock, atomic::{AtomicUsize, Ordering}};
+use tokio_util::sync::CancellationToken;
+
+use crate::WamServerState;
 
 static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
 
+/// A frame for the WebSocket clients, with the channel it belongs to.
+#[derive(Debug, Clone)]
+pub struct WsEvent {
+    /// `None` for events every client receives
+    pub channel_id: Option<i32>,
+    pub frame: Message,
+}
+
 #[derive(Debug)]
 pub struct WsConnection {
     pub id: usize,
-    pub sender: Arc<broadcast::Sender<Message>>
+    pub sender: Arc<broadcast::Sender<WsEvent>>,
+    /// Cancelled when the server wants this client to receive a Close frame
+    pub close_token: CancellationToken,
+    /// Cancelled when the session of the user ends: deleted user or revoked tokens
+    pub end_token: CancellationToken,
+    /// User the client connected as, anonymous clients only get the global events
+    pub user_id: Option<i32>,
+    /// Channels whose events are delivered, kept in sync with the user's memberships
+    pub channels: Arc<RwLock<HashSet<i32>>>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -19,23 +39,80 @@ pub struct WsMessage<T: Serialize> {
     pub message: T,
 }
 
+/// Payload of the `message` event, and item of the `message_batch` one
+#[derive(Serialize)]
+pub struct NewMessage<'a> {
+    #[serde(flatten)]
+    pub message: &'a entity::message::Model,
+    /// Root of the thread, for replies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root_id: Option<i32>,
+}
+
 impl WsConnection {
-    pub fn new(sender: &Arc<broadcast::Sender<Message>>) -> Self {
+    pub fn new(sender: &Arc<broadcast::Sender<WsEvent>>, user_id: Option<i32>, channels: HashSet<i32>) -> Self {
         let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
-        Self { id, sender: Arc::clone(sender) }
+        Self {
+            id,
+            sender: Arc::clone(sender),
+            close_token: CancellationToken::new(),
+            end_token: CancellationToken::new(),
+            user_id,
+            channels: Arc::new(RwLock::new(channels)),
+        }
+    }
+
+    pub fn close(&self) {
+        self.close_token.cancel();
+    }
+}
+
+/// Whether a client subscribed to `channels` gets an event of `channel_id`.
+pub fn is_delivered(channels: &RwLock<HashSet<i32>>, channel_id: Option<i32>) -> bool {
+    match channel_id {
+        None => true,
+        Some(channel_id) => channels.read().unwrap().contains(&channel_id),
     }
 }
 
-pub fn broadcast_message<T: Serialize>(sender: &Arc<broadcast::Sender<axum::extract::ws::Message>>, msg_type: String, message: T) -> Result<(), broadcast::error::SendError<Message>> {
+/// Start or stop delivering a channel's events to the open connections of a user.
+pub fn update_membership(state: &WamServerState, user_id: i32, channel_id: i32, member: bool) {
+    let connections = state.ws_connections.lock().unwrap();
+    for conn in connections.iter().filter(|conn| conn.user_id == Some(user_id)) {
+        let mut channels = conn.channels.write().unwrap();
+        if member {
+            channels.insert(channel_id);
+        } else {
+            channels.remove(&channel_id);
+        }
+    }
+}
+
+/// Close the open connections of a user, whose credentials no longer hold.
+pub fn end_sessions(state: &WamServerState, user_id: i32) {
+    let connections = state.ws_connections.lock().unwrap();
+    for conn in connections.iter().filter(|conn| conn.user_id == Some(user_id)) {
+        conn.end_token.cancel();
+    }
+}
+
+/// Send an event to every client.
+pub fn broadcast_message<T: Serialize>(state: &WamServerState, msg_type: String, message: T) {
+    broadcast_to_channel(state, None, msg_type, message)
+}
+
+/// Send an event to the members of a channel, or to every client when `channel_id` is `None`.
+/// Failed sends are counted and logged here, callers have nothing to handle.
+pub fn broadcast_to_channel<T: Serialize>(state: &WamServerState, channel_id: Option<i32>, msg_type: String, message: T) {
     let msg_to_send = WsMessage {
         msg_type,
         message,
     };
 
     let msg_json = serde_json::to_string(&msg_to_send).unwrap_or_else(|_| "{}".to_string());
-    if let Err(e) = sender.send(axum::extract::ws::Message::Text(msg_json.to_string().into())) {
+    let event = WsEvent { channel_id, frame: axum::extract::ws::Message::Text(msg_json.into()) };
+    if let Err(e) = state.ws_sender.send(event) {
+        state.metrics.ws_dropped_sends.inc();
         error!("Error broadcasting message to WebSocket clients: {}", e);
     }
-
-    Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,107 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+/// Source label of created messages.
+pub const SOURCE_HTTP: &str = "http";
+pub const SOURCE_KAFKA: &str = "kafka";
+
+/// Prometheus metrics of the message, vehicle and WebSocket pipelines.
+pub struct WamMetrics {
+    registry: Registry,
+    pub messages_created: IntCounterVec,
+    pub kafka_parse_failures: IntCounter,
+    pub kafka_send_failures: IntCounter,
+    pub sytral_fetch_duration: Histogram,
+    pub sytral_vehicles: IntGauge,
+    pub ws_connections: IntGauge,
+    pub ws_lagged_messages: IntCounter,
+    pub ws_dropped_sends: IntCounter,
+}
+
+impl WamMetrics {
+    pub fn new() -> Self {
+        let messages_created = IntCounterVec::new(
+            Opts::new("wam_messages_created_total", "Messages stored in database, by source"),
+            &["source"],
+        ).unwrap();
+        let kafka_parse_failures = IntCounter::new(
+            "wam_kafka_parse_failures_total",
+            "Kafka records that could not be parsed as a message",
+        ).unwrap();
+        let kafka_send_failures = IntCounter::new(
+            "wam_kafka_send_failures_total",
+            "Vehicle lists that could not be sent to Kafka",
+        ).unwrap();
+        let sytral_fetch_duration = Histogram::with_opts(HistogramOpts::new(
+            "wam_sytral_fetch_duration_seconds",
+            "Duration of SYTRAL vehicle monitoring requests",
+        )).unwrap();
+        let sytral_vehicles = IntGauge::new(
+            "wam_sytral_vehicles",
+            "Number of vehicles returned by the last SYTRAL fetch",
+        ).unwrap();
+        let ws_connections = IntGauge::new(
+            "wam_ws_connections",
+            "Currently open WebSocket connections",
+        ).unwrap();
+        let ws_lagged_messages = IntCounter::new(
+            "wam_ws_lagged_messages_total",
+            "Broadcast messages skipped by WebSocket clients that could not keep up",
+        ).unwrap();
+        let ws_dropped_sends = IntCounter::new(
+            "wam_ws_dropped_sends_total",
+            "Broadcast messages dropped because no WebSocket client was listening",
+        ).unwrap();
+
+        // Expose both sources from the start so rates can be computed from zero
+        for source in [SOURCE_HTTP, SOURCE_KAFKA] {
+            messages_created.with_label_values(&[source]);
+        }
+
+        let registry = Registry::new();
+        registry.register(Box::new(messages_created.clone())).unwrap();
+        registry.register(Box::new(kafka_parse_failures.clone())).unwrap();
+        registry.register(Box::new(kafka_send_failures.clone())).unwrap();
+        registry.register(Box::new(sytral_fetch_duration.clone())).unwrap();
+        registry.register(Box::new(sytral_vehicles.clone())).unwrap();
+        registry.register(Box::new(ws_connections.clone())).unwrap();
+        registry.register(Box::new(ws_lagged_messages.clone())).unwrap();
+        registry.register(Box::new(ws_dropped_sends.clone())).unwrap();
+
+        WamMetrics {
+            registry,
+            messages_created,
+            kafka_parse_failures,
+            kafka_send_failures,
+            sytral_fetch_duration,
+            sytral_vehicles,
+            ws_connections,
+            ws_lagged_messages,
+            ws_dropped_sends,
+        }
+    }
+
+    pub fn message_created(&self, source: &str) {
+        self.messages_created.with_label_values(&[source]).inc();
+    }
+
+    pub fn messages_created(&self, source: &str, count: usize) {
+        self.messages_created.with_label_values(&[source]).inc_by(count as u64);
+    }
+
+    /// Render all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap_or_default();
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+impl Default for WamMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
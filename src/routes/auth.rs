@@ -0,0 +1,147 @@
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Json;
+use chrono::Utc;
+use entity::api_key::Scope;
+use entity::user::Role;
+use log::info;
+use sea_orm::DbErr;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::auth::{reject_password, verify_password, NewApiKey, TokenKind, TokenPair};
+use crate::error::WamError;
+use crate::messaging::websocket::end_sessions;
+use crate::routes::extract::{CurrentUser, JsonBody, PathParam};
+use crate::WamServerState;
+
+/// Body of `POST /api/auth/login`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Login {
+    email: String,
+    password: String,
+}
+
+/// Body of `POST /api/auth/refresh`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Refresh {
+    refresh_token: String,
+}
+
+/// Response of `POST /api/auth/keys`, the only one to ever show the key
+#[derive(Debug, Serialize)]
+pub struct MintedApiKey {
+    #[serde(flatten)]
+    api_key: entity::api_key::Model,
+    key: String,
+}
+
+/// Resolve the `Authorization: Bearer` or `X-Api-Key` credentials of every `/api` request.
+/// Invalid credentials are rejected even on public routes, valid ones are kept for `CurrentUser`.
+pub async fn authenticate(State(state): State<WamServerState>, mut request: Request, next: Next) -> Result<Response, WamError> {
+    if let Some(user) = CurrentUser::from_headers(&state, request.headers()).await? {
+        request.extensions_mut().insert(user);
+    }
+    Ok(next.run(request).await)
+}
+
+/// Route guard of the admin controls: only admins get through, with a session or an `admin` API key.
+pub async fn require_admin(request: Request, next: Next) -> Result<Response, WamError> {
+    let caller = request.extensions()
+        .get::<CurrentUser>()
+        .ok_or_else(|| WamError::Unauthorized("a bearer token or an API key is required".to_string()))?;
+    if caller.user.role != Role::Admin {
+        return Err(WamError::Forbidden("this requires the admin role".to_string()));
+    }
+    caller.require(Scope::Admin)?;
+    Ok(next.run(request).await)
+}
+
+/// Route guard of the message writes: anonymous requests are rejected, and read-only users may
+/// watch the stream but not post.
+pub async fn require_writer(request: Request, next: Next) -> Result<Response, WamError> {
+    let caller = request.extensions()
+        .get::<CurrentUser>()
+        .ok_or_else(|| WamError::Unauthorized("a bearer token or an API key is required".to_string()))?;
+    if caller.user.role == Role::ReadOnly {
+        return Err(WamError::Forbidden(format!("User {} is read-only", caller.user.id)));
+    }
+    Ok(next.run(request).await)
+}
+
+/// Exchange an email and password for a token pair
+pub async fn login(state: State<WamServerState>, JsonBody(login): JsonBody<Login>) -> Result<Json<TokenPair>, WamError> {
+    // Same answer for an unknown email and a wrong password
+    let rejected = || WamError::Unauthorized("invalid email or password".to_string());
+
+    // and the same time, a password is checked either way
+    let user = state.db.get_user_by_email(&login.email).await?;
+    let Some((user, hash)) = user.and_then(|user| user.password_hash.clone().map(|hash| (user, hash))) else {
+        reject_password(login.password).await;
+        return Err(rejected());
+    };
+    if !verify_password(login.password, hash).await {
+        return Err(rejected());
+    }
+
+    info!("User {} logged in", user.id);
+    Ok(Json(state.auth.issue(&user)?))
+}
+
+/// Exchange a refresh token for a new token pair
+pub async fn refresh(state: State<WamServerState>, JsonBody(refresh): JsonBody<Refresh>) -> Result<Json<TokenPair>, WamError> {
+    let verified = state.auth.verify(&refresh.refresh_token, TokenKind::Refresh)?;
+    // Deleted users and revoked sessions cannot be extended
+    let user = state.db.get_user(verified.user_id)
+        .await
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(_) => WamError::Unauthorized("the user of this token no longer exists".to_string()),
+            e => e.into(),
+        })?;
+    verified.check_version(&user)?;
+    Ok(Json(state.auth.issue(&user)?))
+}
+
+/// Revoke every token of the current user, on all their devices
+pub async fn logout(state: State<WamServerState>, caller: CurrentUser) -> Result<StatusCode, WamError> {
+    caller.require(Scope::Admin)?;
+    state.db.revoke_tokens(caller.user.id).await?;
+    end_sessions(&state, caller.user.id);
+    info!("User {} logged out", caller.user.id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Mint an API key for the current user. An API key may only mint others with the `admin` scope.
+pub async fn create_api_key(state: State<WamServerState>, caller: CurrentUser, JsonBody(api_key): JsonBody<entity::api_key::Model>) -> Result<(StatusCode, Json<MintedApiKey>), WamError> {
+    caller.require(Scope::Admin)?;
+    api_key.validate()?;
+    if api_key.scopes.0.contains(&Scope::Admin) && caller.user.role != Role::Admin {
+        return Err(WamError::Forbidden("only admins may mint keys with the admin scope".to_string()));
+    }
+    if api_key.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Err(WamError::Validation("expires_at must be in the future".to_string()));
+    }
+
+    let new_key = NewApiKey::generate();
+    let api_key = state.db.create_api_key(caller.user.id, api_key, new_key.prefix, new_key.hash).await?;
+    info!("User {} minted API key {}", caller.user.id, api_key.id);
+    Ok((StatusCode::CREATED, Json(MintedApiKey { api_key, key: new_key.key })))
+}
+
+/// API keys of the current user, without the keys themselves
+pub async fn get_api_keys(state: State<WamServerState>, caller: CurrentUser) -> Result<Json<Vec<entity::api_key::Model>>, WamError> {
+    caller.require(Scope::Admin)?;
+    Ok(Json(state.db.get_api_keys(caller.user.id).await?))
+}
+
+/// Revoke an API key of the current user, requests made with it fail from then on
+pub async fn revoke_api_key(state: State<WamServerState>, caller: CurrentUser, PathParam(id): PathParam<i32>) -> Result<StatusCode, WamError> {
+    caller.require(Scope::Admin)?;
+    state.db.delete_api_key(id, caller.user.id).await?;
+    info!("User {} revoked API key {}", caller.user.id, id);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,74 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use entity::api_key::Scope;
+use log::info;
+use sea_orm::SqlErr;
+use serde::Deserialize;
+
+use crate::error::WamError;
+use crate::messaging::websocket::update_membership;
+use crate::routes::extract::{CurrentUser, JsonBody, PathParam};
+use crate::WamServerState;
+
+/// Body of `POST /api/channel`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewChannel {
+    name: String,
+}
+
+/// Body of `POST /api/channel/{id}/members`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewMember {
+    user_id: i32,
+}
+
+pub async fn create_channel(state: State<WamServerState>, JsonBody(channel): JsonBody<NewChannel>) -> Result<(StatusCode, Json<entity::channel::Model>), WamError> {
+    let name = channel.name.trim();
+    if name.is_empty() {
+        return Err(WamError::Validation("name must not be empty".to_string()));
+    }
+    let channel = state.db.create_channel(name.to_string())
+        .await
+        .map_err(|e| match e.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => WamError::Conflict(format!("A channel named {} already exists", name)),
+            _ => e.into(),
+        })?;
+    info!("Channel {} created", channel.name);
+    Ok((StatusCode::CREATED, Json(channel)))
+}
+
+pub async fn get_channels(state: State<WamServerState>) -> Result<Json<Vec<entity::channel::Model>>, WamError> {
+    Ok(Json(state.db.get_channels().await?))
+}
+
+pub async fn get_channel_members(state: State<WamServerState>, caller: CurrentUser, PathParam(id): PathParam<i32>) -> Result<Json<Vec<entity::user::Model>>, WamError> {
+    caller.require(Scope::UserRead)?;
+    Ok(Json(state.db.get_channel_members(id).await?))
+}
+
+pub async fn add_channel_member(state: State<WamServerState>, PathParam(id): PathParam<i32>, JsonBody(member): JsonBody<NewMember>) -> Result<StatusCode, WamError> {
+    state.db.get_channel(id).await?;
+    state.db.add_channel_member(id, member.user_id)
+        .await
+        .map_err(|e| match e.sql_err() {
+            Some(SqlErr::ForeignKeyConstraintViolation(_)) => WamError::NotFound(format!("User with id {} not found", member.user_id)),
+            Some(SqlErr::UniqueConstraintViolation(_)) => WamError::Conflict(format!("User {} is already a member of channel {}", member.user_id, id)),
+            _ => e.into(),
+        })?;
+    info!("User {} joined channel {}", member.user_id, id);
+
+    // Open connections of the user start receiving the channel right away
+    update_membership(&state, member.user_id, id, true);
+    Ok(StatusCode::CREATED)
+}
+
+pub async fn remove_channel_member(state: State<WamServerState>, PathParam((id, user_id)): PathParam<(i32, i32)>) -> Result<StatusCode, WamError> {
+    state.db.remove_channel_member(id, user_id).await?;
+    info!("User {} left channel {}", user_id, id);
+
+    update_membership(&state, user_id, id, false);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,205 @@
+use axum::body::Bytes;
+use axum::extract::{FromRequest, FromRequestParts, OptionalFromRequestParts, Request};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::request::Parts;
+use axum::http::HeaderMap;
+use axum_macros::{FromRequest, FromRequestParts};
+use chrono::{DateTime, TimeDelta, Utc};
+use entity::api_key::{Scope, Scopes};
+use entity::user;
+use sea_orm::DbErr;
+
+use crate::auth::{hash_api_key, TokenKind, API_KEY_PREFIX};
+use crate::error::WamError;
+use crate::WamServerState;
+
+/// `axum::Json` whose rejections are reported as `WamError` problem details.
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(WamError))]
+pub struct JsonBody<T>(pub T);
+
+/// A list of JSON values, sent either as a JSON array or, with an `application/x-ndjson`
+/// content type, as one value per line. Items are left for the handler to validate one by one.
+pub struct JsonItems(pub Vec<serde_json::Value>);
+
+impl<S: Send + Sync> FromRequest<S> for JsonItems {
+    type Rejection = WamError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let essence = req.headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        let ndjson = essence == "application/x-ndjson" || essence == "application/ndjson";
+        // Like `axum::Json`, `+json` types are JSON too
+        let json = essence == "application/json" || (essence.starts_with("application/") && essence.ends_with("+json"));
+        if !ndjson && !json {
+            return Err(WamError::UnsupportedMediaType(
+                "Expected request with `Content-Type: application/json` or `application/x-ndjson`".to_string()));
+        }
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(|rejection| WamError::Validation(rejection.body_text()))?;
+
+        if !ndjson {
+            return serde_json::from_slice(&body)
+                .map(JsonItems)
+                .map_err(|e| json_error(e, "expected a JSON array".to_string()));
+        }
+
+        let mut items = Vec::new();
+        for (i, line) in body.split(|b| *b == b'\n').enumerate() {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let item = serde_json::from_slice(line)
+                .map_err(|e| json_error(e, format!("line {}", i + 1)))?;
+            items.push(item);
+        }
+        Ok(JsonItems(items))
+    }
+}
+
+/// Broken JSON is malformed, well-formed JSON of the wrong shape is invalid.
+fn json_error(e: serde_json::Error, context: String) -> WamError {
+    let detail = format!("{}: {}", context, e);
+    if e.is_syntax() || e.is_eof() {
+        WamError::Malformed(detail)
+    } else {
+        WamError::Validation(detail)
+    }
+}
+
+/// `axum::extract::Path` whose rejections are reported as `WamError` problem details.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(WamError))]
+pub struct PathParam<T>(pub T);
+
+/// `axum::extract::Query` whose rejections are reported as `WamError` problem details.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(WamError))]
+pub struct QueryParams<T>(pub T);
+
+/// The user authenticated by the `Authorization: Bearer` access token or API key, or by the
+/// `X-Api-Key` header of the request. `Option<CurrentUser>` accepts anonymous requests but still
+/// rejects invalid credentials.
+#[derive(Debug, Clone)]
+pub struct CurrentUser {
+    pub user: user::Model,
+    /// Scopes of the API key of the request, `None` for a login session which may do anything
+    pub scopes: Option<Scopes>,
+    /// When the credentials stop being valid, `None` for API keys without expiry
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// How long `last_used_at` of an API key may lag behind, to spare a write on every request
+const KEY_LAST_USED_RESOLUTION: TimeDelta = TimeDelta::minutes(1);
+
+impl CurrentUser {
+    /// Resolve the user of the credentials of the request, `None` without credentials.
+    pub async fn from_headers(state: &WamServerState, headers: &HeaderMap) -> Result<Option<Self>, WamError> {
+        if let Some(value) = headers.get(API_KEY_HEADER) {
+            let key = value.to_str()
+                .map_err(|_| WamError::Unauthorized("invalid X-Api-Key header".to_string()))?;
+            return CurrentUser::from_api_key(state, key.trim()).await.map(Some);
+        }
+        match bearer_token(headers)? {
+            Some(token) => CurrentUser::from_token(state, token).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Resolve the user of an access token or an API key.
+    pub async fn from_token(state: &WamServerState, token: &str) -> Result<Self, WamError> {
+        if token.starts_with(API_KEY_PREFIX) {
+            return CurrentUser::from_api_key(state, token).await;
+        }
+        let verified = state.auth.verify(token, TokenKind::Access)?;
+        let user = owner(state, verified.user_id, "token").await?;
+        verified.check_version(&user)?;
+        Ok(CurrentUser {
+            user,
+            scopes: None,
+            expires_at: DateTime::from_timestamp(verified.expires_at, 0),
+        })
+    }
+
+    /// Resolve the owner of an unexpired API key, and record its use.
+    pub async fn from_api_key(state: &WamServerState, key: &str) -> Result<Self, WamError> {
+        let api_key = state.db.get_api_key_by_hash(&hash_api_key(key))
+            .await?
+            .ok_or_else(|| WamError::Unauthorized("invalid API key".to_string()))?;
+        let now = Utc::now();
+        if api_key.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(WamError::Unauthorized("this API key has expired".to_string()));
+        }
+        if api_key.last_used_at.is_none_or(|last_used_at| now - last_used_at >= KEY_LAST_USED_RESOLUTION) {
+            state.db.touch_api_key(api_key.id, now).await?;
+        }
+        Ok(CurrentUser {
+            user: owner(state, api_key.user_id, "API key").await?,
+            scopes: Some(api_key.scopes),
+            expires_at: api_key.expires_at,
+        })
+    }
+
+    /// An admin, with credentials granting the `admin` scope, as `require_admin` demands.
+    pub fn is_admin(&self) -> bool {
+        self.user.role == user::Role::Admin && self.require(Scope::Admin).is_ok()
+    }
+
+    /// Fail unless the credentials of the request grant the scope.
+    pub fn require(&self, scope: Scope) -> Result<(), WamError> {
+        match &self.scopes {
+            Some(scopes) if !scopes.allows(scope) => Err(WamError::Forbidden(format!("this API key lacks the {} scope", scope.name()))),
+            _ => Ok(()),
+        }
+    }
+}
+
+async fn owner(state: &WamServerState, user_id: i32, credential: &str) -> Result<user::Model, WamError> {
+    state.db.get_user(user_id)
+        .await
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(_) => WamError::Unauthorized(format!("the user of this {} no longer exists", credential)),
+            e => e.into(),
+        })
+}
+
+/// Credentials are resolved once by the `authenticate` middleware, directly on routers without it.
+impl FromRequestParts<WamServerState> for CurrentUser {
+    type Rejection = WamError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &WamServerState) -> Result<Self, Self::Rejection> {
+        <CurrentUser as OptionalFromRequestParts<WamServerState>>::from_request_parts(parts, state)
+            .await?
+            .ok_or_else(|| WamError::Unauthorized("a bearer token or an API key is required".to_string()))
+    }
+}
+
+impl OptionalFromRequestParts<WamServerState> for CurrentUser {
+    type Rejection = WamError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &WamServerState) -> Result<Option<Self>, Self::Rejection> {
+        if let Some(user) = parts.extensions.get::<CurrentUser>() {
+            return Ok(Some(user.clone()));
+        }
+        CurrentUser::from_headers(state, &parts.headers).await
+    }
+}
+
+/// Token of an `Authorization: Bearer` header, `None` without the header.
+fn bearer_token(headers: &HeaderMap) -> Result<Option<&str>, WamError> {
+    let Some(value) = headers.get(AUTHORIZATION) else {
+        return Ok(None);
+    };
+    value.to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| Some(token.trim()))
+        .ok_or_else(|| WamError::Unauthorized("expected an Authorization: Bearer header".to_string()))
+}
@@ -0,0 +1,76 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Serialize;
+
+use crate::health::TaskStatus;
+use crate::WamServerState;
+
+/// A background loop is reported stale after missing this many poll intervals.
+const STALE_INTERVALS: u64 = 3;
+
+#[derive(Serialize)]
+pub struct Liveness {
+    status: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct ComponentStatus {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BackgroundStatus {
+    enabled: bool,
+    stale: bool,
+    #[serde(flatten)]
+    task: TaskStatus,
+}
+
+#[derive(Serialize)]
+pub struct Readiness {
+    status: &'static str,
+    database: ComponentStatus,
+    kafka: BackgroundStatus,
+    sytral: BackgroundStatus,
+}
+
+/// Liveness: the process is up and serving requests.
+pub async fn health() -> Json<Liveness> {
+    Json(Liveness { status: "ok" })
+}
+
+/// Readiness: the database answers. Stale Kafka or SYTRAL loops only degrade the status,
+/// disabled ones are ignored.
+pub async fn ready(State(state): State<WamServerState>) -> (StatusCode, Json<Readiness>) {
+    let database = match state.db.ping().await {
+        Ok(()) => ComponentStatus { status: "ok", error: None },
+        Err(e) => ComponentStatus { status: "unavailable", error: Some(e.to_string()) },
+    };
+
+    let config = &state.config;
+    let kafka = state.health.kafka();
+    let kafka = BackgroundStatus {
+        enabled: config.kafka.enabled,
+        stale: config.kafka.enabled && kafka.is_stale(config.kafka.poll_interval_secs * STALE_INTERVALS),
+        task: kafka,
+    };
+    let sytral = state.health.sytral();
+    let sytral = BackgroundStatus {
+        enabled: config.sytral.enabled,
+        stale: config.sytral.enabled && sytral.is_stale(config.sytral.poll_interval_secs * STALE_INTERVALS),
+        task: sytral,
+    };
+
+    let (code, status) = if database.error.is_some() {
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+    } else if kafka.stale || sytral.stale {
+        (StatusCode::OK, "degraded")
+    } else {
+        (StatusCode::OK, "ok")
+    };
+
+    (code, Json(Readiness { status, database, kafka, sytral }))
+}
@@ -0,0 +1,13 @@
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+
+use crate::WamServerState;
+
+/// Prometheus scrape endpoint.
+pub async fn metrics(State(state): State<WamServerState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+}
@@ -2,3 +2,8 @@ pub mod socket;
 pub mod pages;
 pub mod services;
 pub mod parameters;
+pub mod channels;
+pub mod auth;
+pub mod health;
+pub mod metrics;
+pub mod extract;
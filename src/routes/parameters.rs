@@ -1,20 +1,33 @@
 use axum::Json;
+use axum::extract::State;
 use serde::Serialize;
-use std::env;
 
+use crate::routes::extract::CurrentUser;
+use crate::WamServerState;
+
+/// Which integrations run, public. The Kafka connection details are only sent to admins.
 #[derive(Serialize)]
 pub struct KafkaParameters {
-    kafka_url: String,
-    kafka_topic: String,
-    kafka_group: String,
+    kafka_enabled: bool,
+    sytral_enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kafka_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kafka_topic: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kafka_group: Option<String>,
 }
 
-pub async fn get_kafka_parameters() -> Json<KafkaParameters> {
+pub async fn get_kafka_parameters(State(state): State<WamServerState>, caller: Option<CurrentUser>) -> Json<KafkaParameters> {
+    let kafka = &state.config.kafka;
+    let admin = caller.is_some_and(|caller| caller.is_admin());
     let params = KafkaParameters {
-        kafka_url: env::var("KAFKA_URL").unwrap_or_default(),
-        kafka_topic: env::var("KAFKA_TOPIC").unwrap_or_default(),
-        kafka_group: env::var("KAFKA_GROUP").unwrap_or_default(),
+        kafka_enabled: kafka.enabled,
+        sytral_enabled: state.config.sytral.enabled,
+        kafka_url: admin.then(|| kafka.url.clone()),
+        kafka_topic: admin.then(|| kafka.topic.clone()),
+        kafka_group: admin.then(|| kafka.group.clone()),
     };
     
     Json(params)
-}
\ No newline at end of file
+}
@@ -1,11 +1,32 @@
-use axum::http::StatusCode;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::{Json};
 use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
 use axum::extract::State;
-use crate::messaging::websocket::{broadcast_message};
-use crate::{WamServerState};
-use log::{info, error};
+use crate::error::WamError;
+#[cfg(feature = "sytral")]
+use crate::messaging::sytral::VehicleList;
+use crate::messaging::placement::Placement;
+#[cfg(feature = "sytral")]
+use crate::messaging::websocket::broadcast_message;
+use crate::messaging::websocket::{broadcast_to_channel, end_sessions, NewMessage};
+use crate::config::OnUserDelete;
+use crate::database::requests::{MessageFilter, UserFilter, Visibility};
+use crate::stats::{message_stats, Bucket, MessageStats};
+use crate::routes::extract::{CurrentUser, JsonBody, JsonItems, PathParam, QueryParams};
+use crate::{metrics, WamServerState};
+use log::info;
+use sea_orm::{DbErr, SqlErr};
 use serde::{Deserialize, Serialize};
+use validator::Validate;
+use entity::api_key::Scope;
+use entity::message::Source;
+use entity::user::Role;
+use entity::validation::{not_blank, MAX_CLIENT_MSG_ID_LEN, MAX_EMAIL_LEN, MAX_NAME_LEN, MAX_PASSWORD_LEN, MAX_TEXT_LEN, MIN_PASSWORD_LEN};
+use crate::auth::hash_password;
 
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -13,77 +34,744 @@ pub struct ApiResponse {
     message: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MessageInfo{
-    nb: u64
+/// Query string of `GET /api/info`
+#[derive(Debug, Deserialize)]
+pub struct InfoQuery {
+    /// Width of the rate buckets, `minute` by default
+    bucket: Option<Bucket>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
 }
 
-// POST 
-#[debug_handler]
-pub async fn create_message(state: State<WamServerState>, Json(message): Json<entity::message::Model>) -> Result<StatusCode, StatusCode>{
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 1000;
+const MAX_BATCH_SIZE: usize = 10_000;
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Set on the response when an idempotency key was already used
+const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+/// Query string of `GET /api/message`
+#[derive(Debug, Deserialize)]
+pub struct MessageQuery {
+    limit: Option<u64>,
+    after: Option<i32>,
+    before: Option<i32>,
+    user_id: Option<i32>,
+    channel_id: Option<i32>,
+    contains: Option<String>,
+    /// RFC 3339 lower bound (inclusive) of `created_at`
+    since: Option<DateTime<Utc>>,
+    /// RFC 3339 upper bound (exclusive) of `created_at`
+    until: Option<DateTime<Utc>>,
+    /// `user` embeds the author of each message
+    expand: Option<String>,
+}
+
+/// Query string of `GET /api/message/search`
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// Words to look for, all of them must match
+    q: String,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+/// A search result: the message, an excerpt with the matches highlighted and its relevance
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    message: entity::message::Model,
+    snippet: String,
+    score: f64,
+}
+
+/// Search results, most relevant first
+#[derive(Debug, Serialize)]
+pub struct SearchResults {
+    results: Vec<SearchResult>,
+}
+
+/// Query string of `GET /api/user`
+#[derive(Debug, Deserialize)]
+pub struct UserQuery {
+    limit: Option<u64>,
+    after: Option<i32>,
+    /// Substring of the name or email
+    q: Option<String>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+/// A page of users by increasing id, with the cursor of the next page
+#[derive(Debug, Serialize)]
+pub struct UserList {
+    users: Vec<entity::user::Model>,
+    next_cursor: Option<i32>,
+}
 
-    // First check that user exists
-    let user = state.db.get_user(message.user_id).await;
-    if user.is_err() {
-        let result = format!("User with id {} not found", message.user_id);
-        error!("{result}");
-        return Err(StatusCode::NOT_FOUND);
+/// Body of `POST /api/user`. Server-managed fields (`id`, timestamps...) are rejected with a 422.
+#[derive(Debug, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct NewUser {
+    #[validate(length(min = 1, max = MAX_NAME_LEN), custom(function = not_blank))]
+    name: String,
+    #[validate(email, length(max = MAX_EMAIL_LEN))]
+    email: String,
+    #[validate(length(min = MIN_PASSWORD_LEN, max = MAX_PASSWORD_LEN))]
+    password: Option<String>,
+    #[serde(default)]
+    role: Role,
+}
+
+/// Body of `PUT /api/user/{id}`, the role is changed with `PUT /api/user/{id}/role` only
+#[derive(Debug, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct UserReplace {
+    #[validate(length(min = 1, max = MAX_NAME_LEN), custom(function = not_blank))]
+    name: String,
+    #[validate(email, length(max = MAX_EMAIL_LEN))]
+    email: String,
+    #[validate(length(min = MIN_PASSWORD_LEN, max = MAX_PASSWORD_LEN))]
+    password: Option<String>,
+}
+
+/// Payload of the `user_created` and `user_updated` WebSocket events, which reach every client:
+/// emails are left to `GET /api/user`
+#[derive(Debug, Serialize)]
+pub struct UserEvent<'a> {
+    id: i32,
+    name: &'a str,
+    role: Role,
+    tombstone: bool,
+}
+
+impl<'a> From<&'a entity::user::Model> for UserEvent<'a> {
+    fn from(user: &'a entity::user::Model) -> Self {
+        UserEvent { id: user.id, name: &user.name, role: user.role, tombstone: user.tombstone }
     }
+}
 
-    // Store message in DB
-    let res = state.db.create_message(&message).await;
-    
-    match res {
-        Ok(ser_msg) => {
-            info!("Message successfully stored in database");
-
-            // Broadcast message to WebSocket clients
-            broadcast_message(&state.ws_sender, "message".to_string(), ser_msg)
-                .unwrap_or_else(|e| {
-                    error!("Error broadcasting message to WebSocket clients: {}", e);
-                });
-
-            Ok(StatusCode::OK)
+/// Partial update of a user, absent fields are left untouched
+#[derive(Debug, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct UserPatch {
+    #[validate(length(min = 1, max = MAX_NAME_LEN), custom(function = not_blank))]
+    name: Option<String>,
+    #[validate(email, length(max = MAX_EMAIL_LEN))]
+    email: Option<String>,
+    #[validate(length(min = MIN_PASSWORD_LEN, max = MAX_PASSWORD_LEN))]
+    password: Option<String>,
+}
+
+/// Query string of `DELETE /api/user/{id}`
+#[derive(Debug, Deserialize)]
+pub struct UserDeleteQuery {
+    /// What to do with the user's messages, `database.on_user_delete` by default
+    messages: Option<OnUserDelete>,
+}
+
+/// Payload of the `user_deleted` WebSocket event
+#[derive(Debug, Serialize)]
+pub struct DeletedUserEvent {
+    id: i32,
+    /// What became of the user's messages
+    messages: OnUserDelete,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reassigned_to: Option<i32>,
+}
+
+/// A page of messages, newest first, with the cursors of the neighbour pages
+#[derive(Debug, Serialize)]
+pub struct MessageList {
+    messages: Vec<MessageView>,
+    next_cursor: Option<i32>,
+    prev_cursor: Option<i32>,
+}
+
+/// Body of `PUT /api/message/{id}`, the author of a message never changes
+#[derive(Debug, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct MessageUpdate {
+    #[validate(length(min = 1, max = MAX_TEXT_LEN), custom(function = not_blank))]
+    text: String,
+}
+
+/// Partial update of a message, absent fields are left untouched
+#[derive(Debug, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct MessagePatch {
+    #[validate(length(min = 1, max = MAX_TEXT_LEN), custom(function = not_blank))]
+    text: Option<String>,
+}
+
+/// Payload of the `message_deleted` WebSocket event
+#[derive(Debug, Serialize)]
+pub struct DeletedMessage {
+    id: i32,
+}
+
+/// A message with its number of direct replies, and its author when `expand=user` is asked for
+#[derive(Debug, Serialize)]
+pub struct MessageView {
+    #[serde(flatten)]
+    message: entity::message::Model,
+    reply_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<entity::user::Model>,
+}
+
+/// Response of `GET /api/message/{id}/thread`, the messages in conversation order
+#[derive(Debug, Serialize)]
+pub struct Thread {
+    root_id: i32,
+    messages: Vec<ThreadMessage>,
+}
+
+/// A message of a thread, `depth` replies below the root
+#[derive(Debug, Serialize)]
+pub struct ThreadMessage {
+    #[serde(flatten)]
+    message: entity::message::Model,
+    depth: usize,
+}
+
+/// Outcome of one item of `POST /api/message/batch`, in request order
+#[derive(Debug, Serialize)]
+pub struct BatchItemResult {
+    index: usize,
+    /// The status the item would have got from `POST /api/message`, 200 when stored
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<i32>,
+    /// Stored by a previous request with the same `client_msg_id`, like `Idempotent-Replayed`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    replayed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchResults {
+    created: usize,
+    rejected: usize,
+    results: Vec<BatchItemResult>,
+}
+
+/// Payload of the `message_batch` WebSocket event, sent once per batch
+#[derive(Serialize)]
+pub struct MessageBatch<'a> {
+    messages: Vec<NewMessage<'a>>,
+}
+
+/// Query string of `GET /api/message/{id}`
+#[derive(Debug, Deserialize)]
+pub struct MessageGetQuery {
+    expand: Option<String>,
+}
+
+/// Whether `expand` asks for the author, `user` being the only relation that can be expanded
+fn expand_user(expand: Option<&str>) -> Result<bool, WamError> {
+    let mut user = false;
+    for relation in expand.unwrap_or_default().split(',').filter(|r| !r.is_empty()) {
+        match relation {
+            "user" => user = true,
+            other => return Err(WamError::Validation(format!("cannot expand '{}', expected 'user'", other))),
         }
-        Err(e) => {
-            let result = format!("Error creating message: {}", e);
-            error!("{result}");
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+    Ok(user)
+}
+
+/// Count the replies of the messages, and attach their authors when asked to
+async fn message_views(state: &WamServerState, messages: Vec<entity::message::Model>, with_user: bool) -> Result<Vec<MessageView>, WamError> {
+    let ids: Vec<i32> = messages.iter().map(|m| m.id).collect();
+    let reply_counts = state.db.reply_counts(&ids).await?;
+    let users = if with_user {
+        state.db.get_message_authors(&messages).await?
+    } else {
+        vec![None; messages.len()]
+    };
+    Ok(messages.into_iter()
+        .zip(users)
+        .map(|(message, user)| MessageView {
+            reply_count: reply_counts.get(&message.id).copied().unwrap_or(0),
+            message,
+            user,
+        })
+        .collect())
+}
+
+/// A message of a batch, with the error `POST /api/message` would give for the same body
+fn parse_item(item: serde_json::Value) -> Result<entity::message::Model, WamError> {
+    if !item.is_object() {
+        return Err(WamError::Validation("a message must be a JSON object".to_string()));
+    }
+    serde_json::from_value(item)
+        .map_err(|e| WamError::Validation(format!("Failed to deserialize the JSON body into the target type: {}", e)))
+}
+
+/// Root of the thread a new message will join, `None` when it starts one
+async fn thread_root(state: &WamServerState, parent_id: Option<i32>) -> Result<Option<i32>, WamError> {
+    let Some(parent_id) = parent_id else {
+        return Ok(None);
+    };
+    state.db.thread_root_id(parent_id).await
+        .map(Some)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(_) => WamError::NotFound(format!("Parent message with id {} not found", parent_id)),
+            e => e.into(),
+        })
+}
+
+/// Order the messages of a thread depth first from the root, the replies of a message oldest first.
+/// Walks an explicit stack rather than recursing, chains of replies have no depth limit.
+fn build_thread(root: entity::message::Model, messages: Vec<entity::message::Model>) -> Thread {
+    let mut children: HashMap<i32, Vec<entity::message::Model>> = HashMap::new();
+    for message in messages {
+        if let Some(parent_id) = message.parent_id {
+            children.entry(parent_id).or_default().push(message);
+        }
+    }
+
+    let root_id = root.id;
+    let mut ordered = Vec::new();
+    let mut stack = vec![(root, 0)];
+    while let Some((message, depth)) = stack.pop() {
+        if let Some(replies) = children.remove(&message.id) {
+            stack.extend(replies.into_iter().rev().map(|reply| (reply, depth + 1)));
         }
+        ordered.push(ThreadMessage { message, depth });
+    }
+    Thread { root_id, messages: ordered }
+}
+
+/// The foreign key rejects messages whose author does not exist
+fn author_error(user_id: i32) -> impl FnOnce(DbErr) -> WamError {
+    move |e| match e.sql_err() {
+        Some(SqlErr::ForeignKeyConstraintViolation(_)) => WamError::NotFound(format!("User with id {} not found", user_id)),
+        _ => e.into(),
+    }
+}
+
+/// Only the author of a message, or an admin, may edit or delete it.
+fn check_owner(caller: &CurrentUser, message: &entity::message::Model) -> Result<(), WamError> {
+    caller.require(Scope::MessageWrite)?;
+    if message.user_id != caller.user.id && caller.user.role != Role::Admin {
+        return Err(WamError::Forbidden(format!("User {} is not the author of message {}", caller.user.id, message.id)));
+    }
+    Ok(())
+}
+
+/// Admins read every channel, users the channels they joined, anonymous callers only public messages.
+fn visibility(caller: Option<&CurrentUser>) -> Visibility {
+    match caller {
+        Some(caller) if caller.is_admin() => Visibility::All,
+        Some(caller) => Visibility::Member(caller.user.id),
+        None => Visibility::Public,
+    }
+}
+
+/// Messages of channels the caller cannot read are reported as missing.
+async fn get_visible_message(state: &WamServerState, visibility: Visibility, id: i32) -> Result<entity::message::Model, WamError> {
+    let message = state.db.get_message(id).await?;
+    if !state.db.is_visible(visibility, message.channel_id).await? {
+        return Err(WamError::NotFound(format!("Message with id {} not found", id)));
+    }
+    Ok(message)
+}
+
+/// Users edit their own profile, admins anyone's. API keys need the `admin` scope either way.
+fn check_profile_access(caller: &CurrentUser, id: i32) -> Result<(), WamError> {
+    caller.require(Scope::Admin)?;
+    if caller.user.id != id && caller.user.role != Role::Admin {
+        return Err(WamError::Forbidden(format!("User {} may only edit their own profile", caller.user.id)));
+    }
+    Ok(())
+}
+
+/// The tombstone user keeps the messages of deleted users, it is never edited or deleted.
+async fn check_not_tombstone(state: &WamServerState, id: i32) -> Result<entity::user::Model, WamError> {
+    let user = state.db.get_user(id).await?;
+    if user.tombstone {
+        return Err(WamError::Conflict("the user holding the messages of deleted users cannot be changed".to_string()));
+    }
+    Ok(user)
+}
+
+/// Hash of a new password, `None` when it is left unchanged
+async fn new_password_hash(password: Option<String>) -> Result<Option<String>, WamError> {
+    match password {
+        Some(password) => hash_password(password).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Emails are unique, report a clash in plain words
+fn email_error(email: String) -> impl FnOnce(DbErr) -> WamError {
+    move |e| match e.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => WamError::Conflict(format!("A user with email {} already exists", email)),
+        _ => e.into(),
     }
+}
 
+/// Idempotency key of the request, from the `Idempotency-Key` header or the `client_msg_id` field
+fn idempotency_key(headers: &HeaderMap, message: &entity::message::Model) -> Result<Option<String>, WamError> {
+    let key = match headers.get(IDEMPOTENCY_KEY) {
+        Some(value) => Some(value.to_str()
+            .map_err(|_| WamError::Validation(format!("{} must be visible ASCII", IDEMPOTENCY_KEY)))?
+            .to_string()),
+        None => message.client_msg_id.clone(),
+    };
+    if let Some(key) = &key
+        && (key.is_empty() || key.len() as u64 > MAX_CLIENT_MSG_ID_LEN)
+    {
+        return Err(WamError::Validation(format!("idempotency key must be 1 to {} characters long", MAX_CLIENT_MSG_ID_LEN)));
+    }
+    Ok(key)
 }
 
+// POST: the author is the authenticated user, whatever the body says
 #[debug_handler]
-pub async fn create_user(state: State<WamServerState>, Json(user): Json<entity::user::Model>) -> Result<StatusCode, StatusCode>{
-    let mut result = "Message created successfully".to_string();
-    let res = state.db.create_user(user).await;
-    
-    match res {
-        Ok(_) => {
-            info!("{result}");
-            Ok(StatusCode::OK)
+pub async fn create_message(state: State<WamServerState>, caller: CurrentUser, headers: HeaderMap, JsonBody(mut message): JsonBody<entity::message::Model>) -> Result<Response, WamError>{
+    caller.require(Scope::MessageWrite)?;
+    message.user_id = caller.user.id;
+    message.source = Some(Source::Http);
+    message.client_msg_id = idempotency_key(&headers, &message)?;
+    message.validate()?;
+    Placement::load(&state.db, [&message]).await?.place(&mut message)?;
+    let root_id = thread_root(&state, message.parent_id).await?;
+
+    // Store message in DB
+    let stored = state.db.create_message(&message).await.map_err(author_error(message.user_id))?;
+    if !stored.created {
+        // A retry: answer like the first time, without storing or broadcasting again
+        info!("Message {} replayed for its idempotency key", stored.message.id);
+        return Ok(([(IDEMPOTENT_REPLAYED, "true")], Json(stored.message)).into_response());
+    }
+    let ser_msg = stored.message;
+    info!("Message successfully stored in database");
+    state.metrics.message_created(metrics::SOURCE_HTTP);
+
+    // Broadcast message to WebSocket clients
+    broadcast_to_channel(&state, ser_msg.channel_id, "message".to_string(), NewMessage { message: &ser_msg, root_id });
+
+    Ok(Json(ser_msg).into_response())
+}
+
+/// Create many messages at once, all authored by the authenticated user. Invalid items are
+/// reported and skipped, the valid ones are inserted in a single transaction.
+pub async fn create_messages_batch(state: State<WamServerState>, caller: CurrentUser, JsonItems(items): JsonItems) -> Result<Json<BatchResults>, WamError> {
+    caller.require(Scope::MessageWrite)?;
+    if items.is_empty() || items.len() > MAX_BATCH_SIZE {
+        return Err(WamError::Validation(format!("a batch holds between 1 and {} messages", MAX_BATCH_SIZE)));
+    }
+
+    let parsed: Vec<_> = items.into_iter()
+        .map(parse_item)
+        .map(|item| item.map(|message| entity::message::Model { user_id: caller.user.id, source: Some(Source::Http), ..message }))
+        .collect();
+    let keys: Vec<String> = parsed.iter().flatten().filter_map(|m| m.client_msg_id.clone()).collect();
+    let replayed = state.db.get_messages_by_client_msg_ids(caller.user.id, &keys).await?;
+    let placement = Placement::load(&state.db, parsed.iter().flatten()).await?;
+
+    let mut results = Vec::with_capacity(parsed.len());
+    let mut valid = Vec::new();
+    let mut batch_keys = HashSet::new();
+    for (index, item) in parsed.into_iter().enumerate() {
+        let mut id = None;
+        let mut replayed_item = false;
+        let (status, error) = match item {
+            Err(e) => (e.status(), Some(e.detail())),
+            Ok(message) if let Err(errors) = message.validate() => {
+                (StatusCode::UNPROCESSABLE_ENTITY, Some(WamError::from(errors).detail()))
+            }
+            // Already stored by a previous request, reported like a single replayed POST
+            Ok(message) if let Some(existing) = message.client_msg_id.as_ref().and_then(|key| replayed.get(key)) => {
+                id = Some(existing.id);
+                replayed_item = true;
+                (StatusCode::OK, None)
+            }
+            Ok(message) if message.client_msg_id.as_ref().is_some_and(|key| !batch_keys.insert(key.clone())) => {
+                (StatusCode::CONFLICT, Some("client_msg_id is used twice in the batch".to_string()))
+            }
+            // Replies must point to stored messages, not to other items of the batch
+            Ok(mut message) => match placement.place(&mut message) {
+                Ok(()) => {
+                    valid.push((index, message));
+                    (StatusCode::OK, None)
+                }
+                Err(e) => (e.status(), Some(e.detail())),
+            },
+        };
+        results.push(BatchItemResult { index, status: status.as_u16(), id, replayed: replayed_item, error });
+    }
+
+    let messages: Vec<_> = valid.iter().map(|(_, message)| message.clone()).collect();
+    let saved = state.db.create_messages(&messages).await?;
+    for ((index, _), message) in valid.iter().zip(&saved) {
+        results[*index].id = Some(message.id);
+    }
+    let created = saved.len();
+    let rejected = results.iter().filter(|r| r.id.is_none()).count();
+    info!("Batch of {} message(s) stored in database, {} rejected", created, rejected);
+    state.metrics.messages_created(metrics::SOURCE_HTTP, saved.len());
+
+    if !saved.is_empty() {
+        let parent_ids: Vec<i32> = saved.iter().filter_map(|m| m.parent_id).collect();
+        let roots = state.db.thread_root_ids(&parent_ids).await?;
+        // One event per channel, each only reaching the members of its channel
+        let mut channels: BTreeMap<Option<i32>, Vec<NewMessage>> = BTreeMap::new();
+        for message in &saved {
+            channels.entry(message.channel_id).or_default().push(NewMessage {
+                message,
+                root_id: message.parent_id.and_then(|parent_id| roots.get(&parent_id).copied()),
+            });
+        }
+        for (channel_id, messages) in channels {
+            broadcast_to_channel(&state, channel_id, "message_batch".to_string(), MessageBatch { messages });
         }
-        Err(e) => {
-            result = format!("Error creating user: {}", e);
-            error!("{result}");
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    Ok(Json(BatchResults {
+        created,
+        rejected,
+        results,
+    }))
+}
+
+pub async fn get_message(state: State<WamServerState>, caller: Option<CurrentUser>, PathParam(id): PathParam<i32>, QueryParams(query): QueryParams<MessageGetQuery>) -> Result<Json<MessageView>, WamError> {
+    let with_user = expand_user(query.expand.as_deref())?;
+    let message = get_visible_message(&state, visibility(caller.as_ref()), id).await?;
+    let view = message_views(&state, vec![message], with_user).await?.remove(0);
+    Ok(Json(view))
+}
+
+/// The whole conversation the message belongs to, from the root of its thread
+pub async fn get_thread(state: State<WamServerState>, caller: Option<CurrentUser>, PathParam(id): PathParam<i32>) -> Result<Json<Thread>, WamError> {
+    // Replies stay in the channel of their parent, so the whole thread is visible or none of it
+    get_visible_message(&state, visibility(caller.as_ref()), id).await?;
+    let root_id = state.db.thread_root_id(id).await?;
+    let mut messages = state.db.get_thread(root_id).await?.into_iter();
+    // Oldest first, so the root comes first
+    let root = messages.next()
+        .ok_or_else(|| WamError::NotFound(format!("Message with id {} not found", id)))?;
+    Ok(Json(build_thread(root, messages.collect())))
+}
+
+// PUT: replace the text
+pub async fn update_message(state: State<WamServerState>, caller: CurrentUser, PathParam(id): PathParam<i32>, JsonBody(update): JsonBody<MessageUpdate>) -> Result<Json<entity::message::Model>, WamError> {
+    update.validate()?;
+    check_owner(&caller, &state.db.get_message(id).await?)?;
+    let updated = state.db.update_message(id, Some(update.text)).await?;
+    broadcast_message_updated(&state, &updated);
+    Ok(Json(updated))
+}
+
+// PATCH: change only the given fields
+pub async fn patch_message(state: State<WamServerState>, caller: CurrentUser, PathParam(id): PathParam<i32>, JsonBody(patch): JsonBody<MessagePatch>) -> Result<Json<entity::message::Model>, WamError> {
+    patch.validate()?;
+    check_owner(&caller, &state.db.get_message(id).await?)?;
+    let updated = state.db.update_message(id, patch.text).await?;
+    broadcast_message_updated(&state, &updated);
+    Ok(Json(updated))
+}
+
+pub async fn delete_message(state: State<WamServerState>, caller: CurrentUser, PathParam(id): PathParam<i32>) -> Result<StatusCode, WamError> {
+    check_owner(&caller, &state.db.get_message(id).await?)?;
+    let deleted = state.db.delete_message(id).await?;
+    info!("Message {} deleted", id);
+
+    broadcast_to_channel(&state, deleted.channel_id, "message_deleted".to_string(), DeletedMessage { id });
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn broadcast_message_updated(state: &WamServerState, message: &entity::message::Model) {
+    info!("Message {} updated", message.id);
+    broadcast_to_channel(state, message.channel_id, "message_updated".to_string(), message);
+}
+
+#[debug_handler]
+pub async fn create_user(state: State<WamServerState>, JsonBody(user): JsonBody<NewUser>) -> Result<Json<entity::user::Model>, WamError>{
+    user.validate()?;
+    let email = user.email.clone();
+    let user = entity::user::Model {
+        id: 0,
+        name: user.name,
+        email: user.email,
+        password_hash: new_password_hash(user.password).await?,
+        role: user.role,
+        tombstone: false,
+        token_version: 0,
+        created_at: Default::default(),
+        updated_at: Default::default(),
+    };
+    let user = state.db.create_user(user).await.map_err(email_error(email))?;
+    info!("User created successfully");
+    broadcast_user_event(&state, "user_created", UserEvent::from(&user));
+    Ok(Json(user))
+}
+
+pub async fn get_user(state: State<WamServerState>, caller: CurrentUser, PathParam(id): PathParam<i32>) -> Result<Json<entity::user::Model>, WamError> {
+    caller.require(Scope::UserRead)?;
+    Ok(Json(state.db.get_user(id).await?))
+}
+
+// PUT: replace name and email
+pub async fn update_user(state: State<WamServerState>, caller: CurrentUser, PathParam(id): PathParam<i32>, JsonBody(user): JsonBody<UserReplace>) -> Result<Json<entity::user::Model>, WamError> {
+    check_profile_access(&caller, id)?;
+    check_not_tombstone(&state, id).await?;
+    user.validate()?;
+    let password_hash = new_password_hash(user.password).await?;
+    let revoked = password_hash.is_some();
+    let updated = state.db.update_user(id, Some(user.name), Some(user.email.clone()), password_hash)
+        .await
+        .map_err(email_error(user.email))?;
+    info!("User {} updated", id);
+    if revoked {
+        end_sessions(&state, id);
+    }
+    broadcast_user_event(&state, "user_updated", UserEvent::from(&updated));
+    Ok(Json(updated))
+}
+
+// PATCH: change only the given fields
+pub async fn patch_user(state: State<WamServerState>, caller: CurrentUser, PathParam(id): PathParam<i32>, JsonBody(patch): JsonBody<UserPatch>) -> Result<Json<entity::user::Model>, WamError> {
+    check_profile_access(&caller, id)?;
+    check_not_tombstone(&state, id).await?;
+    patch.validate()?;
+    let password_hash = new_password_hash(patch.password).await?;
+    let revoked = password_hash.is_some();
+    let updated = state.db.update_user(id, patch.name, patch.email.clone(), password_hash)
+        .await
+        .map_err(email_error(patch.email.unwrap_or_default()))?;
+    info!("User {} updated", id);
+    if revoked {
+        end_sessions(&state, id);
+    }
+    broadcast_user_event(&state, "user_updated", UserEvent::from(&updated));
+    Ok(Json(updated))
+}
+
+/// Body of `PUT /api/user/{id}/role`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoleChange {
+    role: Role,
+}
+
+pub async fn set_user_role(state: State<WamServerState>, PathParam(id): PathParam<i32>, JsonBody(change): JsonBody<RoleChange>) -> Result<Json<entity::user::Model>, WamError> {
+    let user = check_not_tombstone(&state, id).await?;
+    if user.role == Role::Admin && change.role != Role::Admin && state.db.count_users_with_role(Role::Admin).await? <= 1 {
+        return Err(WamError::Conflict(format!("User {} is the last admin, promote another user first", id)));
+    }
+    let updated = state.db.set_user_role(id, change.role).await?;
+    info!("User {} is now {:?}", id, updated.role);
+    end_sessions(&state, id);
+    broadcast_user_event(&state, "user_updated", UserEvent::from(&updated));
+    Ok(Json(updated))
+}
+
+pub async fn delete_user(state: State<WamServerState>, PathParam(id): PathParam<i32>, QueryParams(query): QueryParams<UserDeleteQuery>) -> Result<StatusCode, WamError> {
+    let policy = query.messages.unwrap_or(state.config.database.on_user_delete);
+    check_not_tombstone(&state, id).await?;
+    if policy == OnUserDelete::Restrict {
+        let count = state.db.count_user_messages(id).await?;
+        if count > 0 {
+            return Err(WamError::Conflict(format!("User {} still has {} message(s), delete them with messages=cascade or reassign them with messages=tombstone", id, count)));
         }
     }
+
+    let deleted = state.db.delete_user(id, policy).await?;
+    info!("User {} deleted, {} message(s) {:?}", id, deleted.messages, policy);
+    end_sessions(&state, id);
+
+    broadcast_user_event(&state, "user_deleted", DeletedUserEvent {
+        id,
+        messages: policy,
+        reassigned_to: deleted.reassigned_to,
+    });
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn broadcast_user_event<T: Serialize>(state: &WamServerState, msg_type: &str, user: T) {
+    broadcast_to_channel(state, None, msg_type.to_string(), user);
 }
 
-pub async fn get_messages(state: State<WamServerState>) -> Json<Vec<entity::message::Model>> {
-    let messages = state.db.get_messages().await.unwrap();
-    Json(messages)
+pub async fn get_messages(state: State<WamServerState>, caller: Option<CurrentUser>, QueryParams(query): QueryParams<MessageQuery>) -> Result<Json<MessageList>, WamError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(WamError::Validation(format!("limit must be between 1 and {}", MAX_PAGE_SIZE)));
+    }
+    let with_user = expand_user(query.expand.as_deref())?;
+
+    let filter = MessageFilter {
+        visibility: visibility(caller.as_ref()),
+        user_id: query.user_id,
+        channel_id: query.channel_id,
+        contains: query.contains.filter(|text| !text.is_empty()),
+        after: query.after,
+        before: query.before,
+        since: query.since,
+        until: query.until,
+        limit: Some(limit),
+    };
+    let page = state.db.get_messages(&filter).await?;
+
+    Ok(Json(MessageList {
+        messages: message_views(&state, page.messages, with_user).await?,
+        next_cursor: page.next_cursor,
+        prev_cursor: page.prev_cursor,
+    }))
 }
 
-pub async fn get_messages_count(state: State<WamServerState>) -> Json<MessageInfo> {
-    let nb = state.db.get_messages_count().await.unwrap();
-    let info: MessageInfo = MessageInfo { nb: nb };
-    Json(info)
+pub async fn search_messages(state: State<WamServerState>, caller: Option<CurrentUser>, QueryParams(query): QueryParams<SearchQuery>) -> Result<Json<SearchResults>, WamError> {
+    if !state.db.is_sqlite() {
+        return Err(WamError::NotImplemented("full-text search is only available on SQLite".to_string()));
+    }
+    if query.q.trim().is_empty() {
+        return Err(WamError::Validation("q must not be empty".to_string()));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(WamError::Validation(format!("limit must be between 1 and {}", MAX_PAGE_SIZE)));
+    }
+
+    let hits = state.db.search_messages(&query.q, visibility(caller.as_ref()), limit, query.offset.unwrap_or(0)).await?;
+    let results = hits.into_iter()
+        .map(|hit| SearchResult { message: hit.message, snippet: hit.snippet, score: hit.score })
+        .collect();
+    Ok(Json(SearchResults { results }))
+}
+
+pub async fn get_info(state: State<WamServerState>, caller: Option<CurrentUser>, QueryParams(query): QueryParams<InfoQuery>) -> Result<Json<MessageStats>, WamError> {
+    let stats = message_stats(&state, visibility(caller.as_ref()), query.bucket.unwrap_or_default(), query.since, query.until).await?;
+    Ok(Json(stats))
+}
+
+pub async fn get_users(state: State<WamServerState>, caller: CurrentUser, QueryParams(query): QueryParams<UserQuery>) -> Result<Json<UserList>, WamError> {
+    caller.require(Scope::UserRead)?;
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(WamError::Validation(format!("limit must be between 1 and {}", MAX_PAGE_SIZE)));
+    }
+
+    let filter = UserFilter {
+        search: query.q.filter(|q| !q.is_empty()),
+        since: query.since,
+        until: query.until,
+        after: query.after,
+        limit: Some(limit),
+    };
+    let page = state.db.get_users(&filter).await?;
+    Ok(Json(UserList {
+        users: page.users,
+        next_cursor: page.next_cursor,
+    }))
 }
 
-pub async fn get_users(state: State<WamServerState>) -> Json<Vec<entity::user::Model>> {
-    let users = state.db.get_users().await.unwrap_or_else(|_| vec![]);
-    Json(users)
+/// Broadcast a vehicle list to WebSocket clients, as if it came from the SYTRAL poller
+#[cfg(feature = "sytral")]
+pub async fn publish_vehicles(state: State<WamServerState>, JsonBody(vehicles): JsonBody<VehicleList>) -> StatusCode {
+    broadcast_message(&state, "sytral".to_string(), vehicles);
+    StatusCode::OK
 }
\ No newline at end of file
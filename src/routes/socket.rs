@@ -1,38 +1,121 @@
+use std::collections::HashSet;
+
 use axum::{
-    extract::{ws::{Message, WebSocket, WebSocketUpgrade}, State},
+    extract::{ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade}, State},
     response::Response,
 };
+use chrono::{DateTime, Utc};
 use futures::{SinkExt, StreamExt};
-use crate::WamServerState;
-use crate::messaging::websocket::WsConnection;
-use log::{error, info};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use crate::{shutdown, WamServerState};
+use crate::error::WamError;
+use crate::messaging::websocket::{is_delivered, WsConnection};
+use crate::routes::extract::{CurrentUser, QueryParams};
+use log::{debug, error, info};
+
+/// Query string of `GET /api/ws`
+#[derive(Debug, Deserialize)]
+pub struct WsQuery {
+    /// Access token or API key, for browsers that cannot set headers on WebSockets
+    token: Option<String>,
+}
 
+/// Anonymous sessions get the global events, authenticated ones also get their user's channels.
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<WamServerState>,
-) -> Response {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+    user: Option<CurrentUser>,
+    QueryParams(query): QueryParams<WsQuery>,
+) -> Result<Response, WamError> {
+    let user = match (user, query.token) {
+        (Some(user), _) => Some(user),
+        (None, Some(token)) => Some(CurrentUser::from_token(&state, &token).await?),
+        (None, None) => None,
+    };
+    let user_id = user.as_ref().map(|user| user.user.id);
+    let expires_at = user.and_then(|user| user.expires_at);
+    let channels = match user_id {
+        Some(user_id) => state.db.get_user_channel_ids(user_id).await?,
+        None => HashSet::new(),
+    };
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, state, user_id, expires_at, channels)))
+}
+
+/// Close frame sent when the credentials of the session no longer hold.
+fn session_closed(reason: &'static str) -> Message {
+    Message::Close(Some(CloseFrame {
+        code: close_code::POLICY,
+        reason: reason.into(),
+    }))
 }
 
-async fn handle_socket(socket: WebSocket, state: WamServerState) {
+/// A session lasts as long as its credentials, the client reconnects with new ones.
+async fn handle_socket(socket: WebSocket, state: WamServerState, user_id: Option<i32>, expires_at: Option<DateTime<Utc>>, channels: HashSet<i32>) {
     let (mut sender, mut receiver) = socket.split();
 
     // Create a new subscription to the broadcast channel
     let mut rx = state.ws_sender.subscribe();
 
     // Create a new WsConnection and add it to the connections list
-    let ws_conn = WsConnection::new(&state.ws_sender);
+    let ws_conn = WsConnection::new(&state.ws_sender, user_id, channels);
     let conn_id = ws_conn.id;
+    let close_token = ws_conn.close_token.clone();
+    let end_token = ws_conn.end_token.clone();
+    let channels = ws_conn.channels.clone();
     
     {
         let mut connections = state.ws_connections.lock().unwrap();
+        // A client that arrives while shutting down is closed right away
+        if state.shutdown.is_cancelled() {
+            ws_conn.close();
+        }
         connections.push(ws_conn);
+        state.metrics.ws_connections.set(connections.len() as i64);
         info!("New WebSocket connection established: {}", conn_id);
     }
 
     // Handle incoming messages
+    let metrics = state.metrics.clone();
     let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
+        let expiry = async move {
+            match expires_at {
+                Some(expires_at) => tokio::time::sleep((expires_at - Utc::now()).to_std().unwrap_or_default()).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(expiry);
+
+        loop {
+            let msg = tokio::select! {
+                _ = close_token.cancelled() => {
+                    info!("Closing WebSocket connection {}: server shutting down", conn_id);
+                    let _ = sender.send(shutdown::close_message()).await;
+                    break;
+                }
+                _ = end_token.cancelled() => {
+                    info!("Closing WebSocket connection {}: session revoked", conn_id);
+                    let _ = sender.send(session_closed("Session revoked")).await;
+                    break;
+                }
+                _ = &mut expiry => {
+                    info!("Closing WebSocket connection {}: credentials expired", conn_id);
+                    let _ = sender.send(session_closed("Session expired")).await;
+                    break;
+                }
+                msg = rx.recv() => match msg {
+                    Ok(event) if is_delivered(&channels, event.channel_id) => event.frame,
+                    // Event of a channel the client has not joined
+                    Ok(_) => continue,
+                    // A slow client skips what it missed instead of being disconnected
+                    Err(RecvError::Lagged(skipped)) => {
+                        metrics.ws_lagged_messages.inc_by(skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            };
+
             if let Err(e) = sender.send(msg).await {
                 error!("Error sending message to client {}: {}", conn_id, e);
                 break;
@@ -44,12 +127,9 @@ async fn handle_socket(socket: WebSocket, state: WamServerState) {
     let mut recv_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = receiver.next().await {
             match msg {
+                // The stream is read only, messages are posted through the REST API
                 Message::Text(text) => {
-                    info!("Received message from client {}: {}", conn_id, text);
-                    // Broadcast the message to all clients
-                    if let Err(e) = state.ws_sender.send(Message::Text(text)) {
-                        error!("Error broadcasting message: {}", e);
-                    }
+                    debug!("Ignoring message from client {}: {}", conn_id, text);
                 }
                 Message::Close(_) => {
                     info!("Client {} disconnected", conn_id);
@@ -70,6 +150,7 @@ async fn handle_socket(socket: WebSocket, state: WamServerState) {
     {
         let mut connections = state.ws_connections.lock().unwrap();
         connections.retain(|conn| conn.id != conn_id);
+        state.metrics.ws_connections.set(connections.len() as i64);
         info!("WebSocket connection removed: {}", conn_id);
     }
 }
\ No newline at end of file
@@ -0,0 +1,49 @@
+use axum::extract::ws::{close_code, CloseFrame, Message};
+use log::info;
+
+use crate::WamServerState;
+
+/// Resolve when the process receives SIGINT (Ctrl+C) or SIGTERM.
+pub async fn wait_for_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received SIGINT"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
+
+/// Stop background tasks and ask every WebSocket client to close.
+pub fn trigger(state: &WamServerState) {
+    info!("Shutting down: stopping background tasks and closing WebSocket connections");
+    state.shutdown.cancel();
+
+    let connections = state.ws_connections.lock().unwrap();
+    for conn in connections.iter() {
+        conn.close();
+    }
+    info!("Close requested for {} WebSocket connection(s)", connections.len());
+}
+
+/// Close frame sent to clients when the server goes away.
+pub fn close_message() -> Message {
+    Message::Close(Some(CloseFrame {
+        code: close_code::AWAY,
+        reason: "Server shutting down".into(),
+    }))
+}
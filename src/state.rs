@@ -0,0 +1,161 @@
+use std::sync::{Arc, Mutex};
+
+use log::info;
+use tokio::sync::broadcast;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+use crate::auth::AuthKeys;
+use crate::config::WamConfig;
+use crate::database::WamDatabase;
+use crate::error::WamError;
+use crate::health::HealthState;
+use crate::messaging::websocket::{WsConnection, WsEvent};
+use crate::metrics::WamMetrics;
+
+/// Default capacity of the WebSocket broadcast channel.
+pub const DEFAULT_BROADCAST_CAPACITY: usize = 100;
+
+#[derive(Clone)]
+pub struct WamServerState {
+    pub config: Arc<WamConfig>,
+    pub db: Arc<WamDatabase>,
+    pub ws_connections: Arc<Mutex<Vec<WsConnection>>>,
+    pub ws_sender: Arc<broadcast::Sender<WsEvent>>,
+    /// Cancelled once on SIGTERM/SIGINT, observed by the server and background tasks
+    pub shutdown: CancellationToken,
+    pub health: Arc<HealthState>,
+    pub metrics: Arc<WamMetrics>,
+    /// Signs and checks the session tokens
+    pub auth: Arc<AuthKeys>,
+}
+
+/// Background loops that `spawn_background_tasks` may start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackgroundTasks {
+    pub kafka: bool,
+    pub sytral: bool,
+    pub stats: bool,
+}
+
+/// Builds a `WamServerState`, for the binary as well as for embedding and tests.
+#[derive(Default)]
+pub struct WamServerStateBuilder {
+    config: Option<WamConfig>,
+    db: Option<WamDatabase>,
+    broadcast_capacity: Option<usize>,
+    background_tasks: Option<BackgroundTasks>,
+}
+
+impl BackgroundTasks {
+    pub fn all() -> Self {
+        BackgroundTasks { kafka: true, sytral: true, stats: true }
+    }
+
+    pub fn none() -> Self {
+        BackgroundTasks { kafka: false, sytral: false, stats: false }
+    }
+}
+
+impl WamServerState {
+    pub fn builder() -> WamServerStateBuilder {
+        WamServerStateBuilder::default()
+    }
+
+    pub fn get_db(&self) -> &WamDatabase {
+        &self.db
+    }
+
+    /// Start the enabled background loops. They stop by themselves once `shutdown` is cancelled.
+    pub fn spawn_background_tasks(&self) -> JoinSet<()> {
+        let mut tasks: JoinSet<()> = JoinSet::new();
+
+        #[cfg(feature = "kafka")]
+        if self.config.kafka.enabled {
+            let cloned_state: WamServerState = self.clone();
+            tasks.spawn(async move {
+                crate::messaging::kafka::consume_kafka_message(cloned_state).await
+            });
+        } else {
+            info!("Kafka consumer disabled");
+        }
+
+        #[cfg(feature = "sytral")]
+        if self.config.sytral.enabled {
+            let cloned_state: WamServerState = self.clone();
+            tasks.spawn(async move {
+                crate::messaging::sytral::sytral_handler(cloned_state).await;
+            });
+        } else {
+            info!("SYTRAL poller disabled");
+        }
+
+        if self.config.server.stats_interval_secs > 0 {
+            let cloned_state: WamServerState = self.clone();
+            tasks.spawn(async move {
+                crate::stats::publish_stats(cloned_state).await;
+            });
+        } else {
+            info!("Stats publisher disabled");
+        }
+
+        info!("Started {} background task(s)", tasks.len());
+        tasks
+    }
+}
+
+impl WamServerStateBuilder {
+    /// Defaults to `WamConfig::default()`.
+    pub fn config(mut self, config: WamConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Use an already opened database instead of opening `config.database`.
+    pub fn database(mut self, db: WamDatabase) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    pub fn broadcast_capacity(mut self, capacity: usize) -> Self {
+        self.broadcast_capacity = Some(capacity);
+        self
+    }
+
+    /// Restrict the background loops to this set. Loops disabled in the configuration stay disabled.
+    pub fn background_tasks(mut self, tasks: BackgroundTasks) -> Self {
+        self.background_tasks = Some(tasks);
+        self
+    }
+
+    /// Fails when `config.database` cannot be opened or migrated.
+    pub async fn build(self) -> Result<WamServerState, WamError> {
+        let mut config = self.config.unwrap_or_default();
+        if let Some(tasks) = self.background_tasks {
+            config.kafka.enabled &= tasks.kafka;
+            config.sytral.enabled &= tasks.sytral;
+            if !tasks.stats {
+                config.server.stats_interval_secs = 0;
+            }
+        }
+
+        let db = match self.db {
+            Some(db) => db,
+            None => WamDatabase::open(&config.database).await?,
+        };
+
+        // Create broadcast channel for WebSocket messages
+        let (ws_sender, _) = broadcast::channel(self.broadcast_capacity.unwrap_or(DEFAULT_BROADCAST_CAPACITY));
+
+        Ok(WamServerState {
+            auth: Arc::new(AuthKeys::new(&config.auth)),
+            config: Arc::new(config),
+            db: Arc::new(db),
+            ws_connections: Arc::new(Mutex::new(Vec::new())),
+            ws_sender: Arc::new(ws_sender),
+            shutdown: CancellationToken::new(),
+            health: Arc::new(HealthState::default()),
+            metrics: Arc::new(WamMetrics::new()),
+        })
+    }
+}
@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use entity::message::Source;
+
+use crate::database::requests::Visibility;
+use crate::error::WamError;
+use crate::messaging::websocket::broadcast_message;
+use crate::WamServerState;
+
+/// Most buckets a rate can be split into
+pub const MAX_BUCKETS: i64 = 1440;
+/// Buckets returned when no range is given
+const DEFAULT_BUCKETS: i64 = 60;
+/// Users listed in `MessageStats::users`, the most active ones
+pub const MAX_STATS_USERS: u64 = 20;
+
+/// Width of the buckets of a message rate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bucket {
+    Second,
+    #[default]
+    Minute,
+    Hour,
+}
+
+impl Bucket {
+    pub fn seconds(self) -> i64 {
+        match self {
+            Bucket::Second => 1,
+            Bucket::Minute => 60,
+            Bucket::Hour => 3600,
+        }
+    }
+}
+
+/// Message statistics, served by `GET /api/info` and pushed as `stats` WebSocket events
+#[derive(Debug, Serialize)]
+pub struct MessageStats {
+    /// Total number of messages
+    pub nb: u64,
+    /// Message counts of the `MAX_STATS_USERS` most active users, most active first
+    pub users: Vec<UserCount>,
+    pub ingested: Ingested,
+    pub rate: MessageRate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserCount {
+    pub user_id: i32,
+    pub count: i64,
+}
+
+/// Messages stored by each source
+#[derive(Debug, Serialize)]
+pub struct Ingested {
+    pub http: u64,
+    pub kafka: u64,
+}
+
+/// Messages created per bucket over `[since, until)`, oldest first, empty buckets included
+#[derive(Debug, Serialize)]
+pub struct MessageRate {
+    pub bucket: Bucket,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub counts: Vec<RateBucket>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RateBucket {
+    pub start: DateTime<Utc>,
+    pub count: i64,
+}
+
+/// Compute the statistics of the messages the reader may see, with the rate over `[since, until)`.
+/// `since` is rounded down to a bucket boundary, and both bounds default to the last 60 buckets.
+pub async fn message_stats(state: &WamServerState, visibility: Visibility, bucket: Bucket, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Result<MessageStats, WamError> {
+    if !state.db.is_sqlite() {
+        return Err(WamError::NotImplemented("message rates are only available on SQLite".to_string()));
+    }
+
+    let width = bucket.seconds();
+    let until = until.unwrap_or_else(Utc::now);
+    let since = since.unwrap_or(until - TimeDelta::seconds(width * (DEFAULT_BUCKETS - 1)));
+    if since >= until {
+        return Err(WamError::Validation("since must be before until".to_string()));
+    }
+
+    let first = since.timestamp().div_euclid(width);
+    // `until` is exclusive, the bucket it falls in is kept unless it starts exactly there
+    let last = (until.timestamp_millis() - 1).div_euclid(width * 1000);
+    if last - first + 1 > MAX_BUCKETS {
+        return Err(WamError::Validation(format!("the range spans more than {} buckets", MAX_BUCKETS)));
+    }
+    let since = DateTime::from_timestamp(first * width, 0)
+        .ok_or_else(|| WamError::Validation("since is out of range".to_string()))?;
+
+    let mut rates = state.db.get_message_rates(visibility, since, until, width).await?;
+    let counts = (first..=last)
+        .filter_map(|index| Some(RateBucket {
+            start: DateTime::from_timestamp(index * width, 0)?,
+            count: rates.remove(&index).unwrap_or(0),
+        }))
+        .collect();
+
+    let users = state.db.get_user_message_counts(visibility, MAX_STATS_USERS).await?
+        .into_iter()
+        .map(|(user_id, count)| UserCount { user_id, count })
+        .collect();
+
+    let sources = state.db.get_message_source_counts(visibility).await?;
+    Ok(MessageStats {
+        nb: state.db.get_messages_count(visibility).await?,
+        users,
+        ingested: Ingested {
+            http: sources.get(&Source::Http).copied().unwrap_or(0),
+            kafka: sources.get(&Source::Kafka).copied().unwrap_or(0),
+        },
+        rate: MessageRate { bucket, since, until, counts },
+    })
+}
+
+/// Push the statistics of public messages, with the per second rate of the last minute, to
+/// WebSocket clients every `server.stats_interval_secs`. Every client gets the same event, so
+/// channels are left out whoever is listening.
+pub async fn publish_stats(state: WamServerState) {
+    let interval = Duration::from_secs(state.config.server.stats_interval_secs);
+    info!("Publishing stats every {:?}", interval);
+
+    while !state.shutdown.is_cancelled() {
+        // Nobody to tell
+        if state.ws_sender.receiver_count() > 0 {
+            match message_stats(&state, Visibility::Public, Bucket::Second, None, None).await {
+                Ok(stats) => {
+                    broadcast_message(&state, "stats".to_string(), stats);
+                }
+                Err(e) => error!("Error computing stats: {}", e),
+            }
+        }
+
+        tokio::select! {
+            _ = state.shutdown.cancelled() => {},
+            _ = tokio::time::sleep(interval) => {},
+        }
+    }
+
+    info!("Stats publisher stopped");
+}
@@ -0,0 +1,52 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use axum_server::tls_rustls::RustlsConfig;
+use log::{error, info};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::TlsConfig;
+
+/// Load the certificate and key from `config`.
+pub async fn load(config: &TlsConfig) -> std::io::Result<RustlsConfig> {
+    // Only ring is compiled in, make it the process wide provider
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    info!("Loading TLS certificate {} and key {}", config.cert_path, config.key_path);
+    RustlsConfig::from_pem_file(&config.cert_path, &config.key_path).await
+}
+
+/// Reload `rustls` whenever the certificate or key file changes, until `shutdown` is cancelled.
+/// New connections use the new certificate, established ones keep the old one.
+pub async fn watch(rustls: RustlsConfig, config: TlsConfig, shutdown: CancellationToken) {
+    if config.reload_interval_secs == 0 {
+        return;
+    }
+
+    let mut last_modified = modified(&config);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(Duration::from_secs(config.reload_interval_secs)) => {},
+        }
+
+        let current = modified(&config);
+        if current == last_modified {
+            continue;
+        }
+
+        // A half written file fails to load, it is retried at the next check
+        match rustls.reload_from_pem_file(&config.cert_path, &config.key_path).await {
+            Ok(()) => {
+                info!("TLS certificate reloaded from {}", config.cert_path);
+                last_modified = current;
+            }
+            Err(e) => error!("Error reloading TLS certificate: {}", e),
+        }
+    }
+}
+
+fn modified(config: &TlsConfig) -> (Option<SystemTime>, Option<SystemTime>) {
+    let mtime = |path: &str| Path::new(path).metadata().and_then(|m| m.modified()).ok();
+    (mtime(&config.cert_path), mtime(&config.key_path))
+}
@@ -0,0 +1,89 @@
+//! API key scopes and authentication.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use chrono::{TimeDelta, Utc};
+use entity::api_key::{Scope, Scopes};
+use entity::user::Role;
+use serde_json::json;
+
+use common::{api_key_header, bearer, TestApp};
+
+#[test]
+fn admin_scope_allows_everything() {
+    let admin = Scopes(vec![Scope::Admin]);
+    assert!(admin.allows(Scope::MessageWrite));
+    assert!(admin.allows(Scope::UserRead));
+    assert!(admin.allows(Scope::Admin));
+
+    let writer = Scopes(vec![Scope::MessageWrite]);
+    assert!(writer.allows(Scope::MessageWrite));
+    assert!(!writer.allows(Scope::UserRead));
+    assert!(!writer.allows(Scope::Admin));
+
+    assert!(!Scopes::default().allows(Scope::MessageWrite));
+}
+
+#[tokio::test]
+async fn keys_authenticate_their_owner() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let key = app.api_key(&alice, vec![Scope::MessageWrite], None).await;
+
+    let (status, created) = app.send(Method::POST, "/api/message", api_key_header(&key), Some(json!({"text": "hello"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(created["user_id"], alice.id);
+    // Keys are also accepted as bearer tokens
+    let (status, created) = app.send(Method::POST, "/api/message", bearer(&key), Some(json!({"text": "hello"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(created["user_id"], alice.id);
+}
+
+#[tokio::test]
+async fn unknown_and_expired_keys_are_rejected() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let expired = app.api_key(&alice, vec![Scope::MessageWrite], Some(Utc::now() - TimeDelta::hours(1))).await;
+
+    let (status, _) = app.send(Method::GET, "/api/message", api_key_header(&expired), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app.send(Method::GET, "/api/message", api_key_header("wam_unknown"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn keys_are_limited_to_their_scopes() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let read_key = app.api_key(&alice, vec![Scope::UserRead], None).await;
+
+    let (status, _) = app.send(Method::POST, "/api/message", api_key_header(&read_key), Some(json!({"text": "hello"}))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Even on her own message
+    let (_, message) = app.send(Method::POST, "/api/message", bearer(&app.token(&alice)), Some(json!({"text": "hello"}))).await;
+    let uri = format!("/api/message/{}", message["id"]);
+    let (status, _) = app.send(Method::PATCH, &uri, api_key_header(&read_key), Some(json!({"text": "edited"}))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn users_are_read_with_the_user_read_scope_only() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let read_key = app.api_key(&alice, vec![Scope::UserRead], None).await;
+    let write_key = app.api_key(&alice, vec![Scope::MessageWrite], None).await;
+    let uri = format!("/api/user/{}", alice.id);
+
+    for uri in ["/api/user", uri.as_str()] {
+        let (status, _) = app.send(Method::GET, uri, None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = app.send(Method::GET, uri, api_key_header(&write_key), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = app.send(Method::GET, uri, api_key_header(&read_key), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = app.send(Method::GET, uri, bearer(&app.token(&alice)), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
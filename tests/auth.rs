@@ -0,0 +1,99 @@
+//! Session tokens and message ownership.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use entity::user::Role;
+use serde_json::json;
+
+use common::{bearer, TestApp};
+
+#[tokio::test]
+async fn authenticate_resolves_access_tokens() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+
+    let (status, created) = app.send(Method::POST, "/api/message", bearer(&app.token(&alice)), Some(json!({"text": "hello"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(created["user_id"], alice.id);
+}
+
+#[tokio::test]
+async fn authenticate_rejects_missing_and_invalid_tokens() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let refresh_token = app.state.auth.issue(&alice).unwrap().refresh_token;
+
+    let (status, _) = app.send(Method::GET, "/api/auth/keys", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app.send(Method::GET, "/api/auth/keys", bearer("not-a-jwt"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app.send(Method::GET, "/api/auth/keys", bearer(&refresh_token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Public routes are open to anonymous callers, not to bad credentials
+    let (status, _) = app.send(Method::GET, "/api/message", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.send(Method::GET, "/api/message", bearer("not-a-jwt"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn messages_are_edited_by_their_author_or_an_admin() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let bob = app.user("Bob", Role::Member).await;
+    let admin = app.user("Admin", Role::Admin).await;
+
+    let (_, message) = app.send(Method::POST, "/api/message", bearer(&app.token(&alice)), Some(json!({"text": "hello"}))).await;
+    let uri = format!("/api/message/{}", message["id"]);
+
+    let (status, _) = app.send(Method::PUT, &uri, None, Some(json!({"text": "anonymous"}))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app.send(Method::PUT, &uri, bearer(&app.token(&bob)), Some(json!({"text": "not mine"}))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.send(Method::DELETE, &uri, bearer(&app.token(&bob)), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, updated) = app.send(Method::PUT, &uri, bearer(&app.token(&alice)), Some(json!({"text": "edited"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["text"], "edited");
+    assert_eq!(updated["user_id"], alice.id);
+
+    let (status, _) = app.send(Method::DELETE, &uri, bearer(&app.token(&admin)), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn tokens_are_revoked_by_logout_and_password_changes() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let pair = app.state.auth.issue(&alice).unwrap();
+    let refresh = || Some(json!({"refresh_token": pair.refresh_token}));
+
+    let (status, renewed) = app.send(Method::POST, "/api/auth/refresh", None, refresh()).await;
+    assert_eq!(status, StatusCode::OK);
+    let renewed = renewed["access_token"].as_str().unwrap().to_string();
+
+    let (status, _) = app.send(Method::POST, "/api/auth/logout", bearer(&pair.access_token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    for token in [&pair.access_token, &renewed] {
+        let (status, _) = app.send(Method::GET, "/api/auth/keys", bearer(token), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _) = app.send(Method::POST, "/api/auth/refresh", None, refresh()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // A new password ends the sessions opened with the old one
+    let token = app.token(&app.state.db.get_user(alice.id).await.unwrap());
+    let uri = format!("/api/user/{}", alice.id);
+    let (status, _) = app.send(Method::PATCH, &uri, bearer(&token), Some(json!({"password": "a new password"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.send(Method::GET, "/api/auth/keys", bearer(&token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = app.send(Method::POST, "/api/auth/login", None, Some(json!({"email": "alice@example.com", "password": "a new password"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.send(Method::POST, "/api/auth/login", None, Some(json!({"email": "nobody@example.com", "password": "a new password"}))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
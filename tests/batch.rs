@@ -0,0 +1,78 @@
+//! `POST /api/message/batch`, and how it agrees with `POST /api/message`.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use entity::user::Role;
+use serde_json::json;
+
+use common::{bearer, TestApp};
+
+#[tokio::test]
+async fn invalid_items_are_reported_and_the_others_stored() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let bob = app.user("Bob", Role::Member).await;
+    let token = app.token(&alice);
+    let (_, channel) = app.send(Method::POST, "/api/channel", bearer(&app.token(&app.user("Admin", Role::Admin).await)), Some(json!({"name": "private"}))).await;
+
+    let (status, _) = app.send(Method::POST, "/api/message", bearer(&token), Some(json!({"text": "first", "client_msg_id": "k1"}))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let items = json!([
+        {"text": "stored", "user_id": bob.id},
+        {"text": "   "},
+        42,
+        {"text": "again", "client_msg_id": "k1"},
+        {"text": "twice", "client_msg_id": "k2"},
+        {"text": "twice", "client_msg_id": "k2"},
+        {"text": "orphan", "parent_id": 999},
+        {"text": "not a member", "channel_id": channel["id"]},
+        {"user_id": "nope"},
+    ]);
+    let (status, batch) = app.send(Method::POST, "/api/message/batch", bearer(&token), Some(items)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(batch["created"], 2);
+    assert_eq!(batch["rejected"], 6);
+
+    let results = batch["results"].as_array().unwrap();
+    let statuses: Vec<u64> = results.iter().map(|r| r["status"].as_u64().unwrap()).collect();
+    assert_eq!(statuses, vec![200, 422, 422, 200, 200, 409, 404, 403, 422]);
+    assert_eq!(results[2]["error"], "a message must be a JSON object");
+    assert_eq!(results[3]["replayed"], true);
+    assert!(results[4].get("replayed").is_none());
+    assert!(!results[8]["error"].as_str().unwrap().contains("Model"), "{}", results[8]);
+
+    // Stored items belong to the caller, whatever their body says
+    let (_, stored) = app.send(Method::GET, &format!("/api/message/{}", results[0]["id"]), None, None).await;
+    assert_eq!(stored["user_id"], alice.id);
+    let (_, page) = app.send(Method::GET, &format!("/api/message?user_id={}", alice.id), None, None).await;
+    assert_eq!(page["messages"].as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn batches_are_json_arrays_or_ndjson() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let token = app.token(&alice);
+    let array = json!([{"text": "one"}]).to_string();
+
+    let (status, problem) = app.send_raw(Method::POST, "/api/message/batch", bearer(&token), Some(("text/plain", array.clone()))).await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(problem["code"], "unsupported_media_type");
+    let (status, _) = app.send_raw(Method::POST, "/api/message", bearer(&token), Some(("text/plain", json!({"text": "one"}).to_string()))).await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let (status, batch) = app.send_raw(Method::POST, "/api/message/batch", bearer(&token), Some(("application/json; charset=utf-8", array))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(batch["created"], 1);
+
+    let ndjson = "{\"text\": \"two\"}\n\n{\"text\": \"three\"}\n".to_string();
+    let (status, batch) = app.send_raw(Method::POST, "/api/message/batch", bearer(&token), Some(("application/x-ndjson", ndjson))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(batch["created"], 2);
+
+    let (status, problem) = app.send_raw(Method::POST, "/api/message/batch", bearer(&token), Some(("application/x-ndjson", "{\"text\": \n".to_string()))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(problem["code"], "malformed_request");
+}
@@ -0,0 +1,80 @@
+//! Who may read and post the messages of a channel.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use entity::user::Role;
+use serde_json::{json, Value};
+
+use common::{bearer, TestApp};
+
+fn texts(page: &Value) -> Vec<&str> {
+    page["messages"].as_array().unwrap().iter().map(|m| m["text"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn channel_messages_are_only_read_by_members_and_admins() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let bob = app.user("Bob", Role::Member).await;
+    let admin = app.user("Admin", Role::Admin).await;
+    let team = app.channel("team", &[&alice]).await;
+
+    app.message(&alice, "public news", None, None).await;
+    let secret = app.message(&alice, "team secret", None, Some(team.id)).await;
+    let reply = app.message(&alice, "team reply", Some(secret.id), Some(team.id)).await;
+
+    let cases = [
+        (None, vec!["public news"]),
+        (bearer(&app.token(&bob)), vec!["public news"]),
+        (bearer(&app.token(&alice)), vec!["team reply", "team secret", "public news"]),
+        (bearer(&app.token(&admin)), vec!["team reply", "team secret", "public news"]),
+    ];
+    for (credential, expected) in cases {
+        let (status, page) = app.send(Method::GET, "/api/message", credential.clone(), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(texts(&page), expected);
+
+        // Hidden messages look like missing ones
+        let visible = expected.len() > 1;
+        for uri in [format!("/api/message/{}", secret.id), format!("/api/message/{}/thread", reply.id)] {
+            let (status, _) = app.send(Method::GET, &uri, credential.clone(), None).await;
+            assert_eq!(status, if visible { StatusCode::OK } else { StatusCode::NOT_FOUND }, "{uri}");
+        }
+        let (_, hits) = app.send(Method::GET, "/api/message/search?q=secret", credential, None).await;
+        assert_eq!(hits["results"].as_array().unwrap().len(), usize::from(visible), "{hits}");
+    }
+}
+
+#[tokio::test]
+async fn only_members_post_in_a_channel() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let bob = app.user("Bob", Role::Member).await;
+    let admin = app.user("Admin", Role::Admin).await;
+    let team = app.channel("team", &[&alice]).await;
+
+    let (status, problem) = app.send(Method::POST, "/api/message", bearer(&app.token(&bob)), Some(json!({"text": "let me in", "channel_id": team.id}))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(problem["code"], "forbidden");
+
+    // Replies stay in the channel of their parent
+    let (status, root) = app.send(Method::POST, "/api/message", bearer(&app.token(&alice)), Some(json!({"text": "hello team", "channel_id": team.id}))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, reply) = app.send(Method::POST, "/api/message", bearer(&app.token(&alice)), Some(json!({"text": "me again", "parent_id": root["id"]}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(reply["channel_id"], team.id);
+
+    // Joining gives access right away, leaving takes it back
+    let admin_token = app.token(&admin);
+    let members = format!("/api/channel/{}/members", team.id);
+    let (status, _) = app.send(Method::POST, &members, bearer(&admin_token), Some(json!({"user_id": bob.id}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = app.send(Method::GET, &format!("/api/message/{}", root["id"]), bearer(&app.token(&bob)), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = app.send(Method::DELETE, &format!("{}/{}", members, bob.id), bearer(&admin_token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = app.send(Method::GET, &format!("/api/message/{}", root["id"]), bearer(&app.token(&bob)), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
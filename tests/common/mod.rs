@@ -0,0 +1,142 @@
+//! Test server on an in-memory database, driven through the public router.
+#![allow(dead_code)]
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use chrono::{DateTime, Utc};
+use entity::api_key::{self, Scope, Scopes};
+use entity::{channel, message};
+use entity::user::{self, Role};
+use sea_orm::{ActiveModelTrait, Set};
+use serde_json::Value;
+use tower::ServiceExt;
+use wamserver::auth::NewApiKey;
+use wamserver::config::{DatabaseConfig, WamConfig};
+use wamserver::database::WamDatabase;
+use wamserver::{build_app, BackgroundTasks, WamServerState};
+
+pub struct TestApp {
+    pub state: WamServerState,
+    pub app: Router,
+}
+
+impl TestApp {
+    /// A server on a fresh in-memory database, without background tasks.
+    pub async fn new() -> Self {
+        Self::with_config(WamConfig::default()).await
+    }
+
+    pub async fn with_config(mut config: WamConfig) -> Self {
+        config.database = DatabaseConfig { url: "sqlite::memory:".to_string(), ..config.database };
+        let db = WamDatabase::open(&config.database).await.unwrap();
+        let state = WamServerState::builder()
+            .config(config)
+            .database(db)
+            .background_tasks(BackgroundTasks::none())
+            .build()
+            .await
+            .unwrap();
+        TestApp { app: build_app(state.clone()), state }
+    }
+
+    pub async fn user(&self, name: &str, role: Role) -> user::Model {
+        self.state.db.create_user(user::Model {
+            id: 0,
+            name: name.to_string(),
+            email: format!("{}@example.com", name.to_lowercase()),
+            password_hash: None,
+            role,
+            tombstone: false,
+            token_version: 0,
+            created_at: Default::default(),
+            updated_at: Default::default(),
+        }).await.unwrap()
+    }
+
+    /// A message stored directly, without the checks of `POST /api/message`.
+    pub async fn message(&self, user: &user::Model, text: &str, parent_id: Option<i32>, channel_id: Option<i32>) -> message::Model {
+        self.state.db.create_message(&message::Model {
+            id: 0,
+            text: text.to_string(),
+            user_id: user.id,
+            client_msg_id: None,
+            parent_id,
+            channel_id,
+            source: None,
+            created_at: Default::default(),
+            updated_at: Default::default(),
+        }).await.unwrap().message
+    }
+
+    /// A channel with the given members.
+    pub async fn channel(&self, name: &str, members: &[&user::Model]) -> channel::Model {
+        let channel = self.state.db.create_channel(name.to_string()).await.unwrap();
+        for member in members {
+            self.state.db.add_channel_member(channel.id, member.id).await.unwrap();
+        }
+        channel
+    }
+
+    /// Move a message back in time, `created_at` being set on insert.
+    pub async fn backdate(&self, message: &message::Model, created_at: DateTime<Utc>) {
+        message::ActiveModel { id: Set(message.id), created_at: Set(created_at), ..Default::default() }
+            .update(&self.state.db.conn)
+            .await
+            .unwrap();
+    }
+
+    /// An access token of the user, as `POST /api/auth/login` hands out.
+    pub fn token(&self, user: &user::Model) -> String {
+        self.state.auth.issue(user).unwrap().access_token
+    }
+
+    /// A new API key of the user, in clear.
+    pub async fn api_key(&self, user: &user::Model, scopes: Vec<Scope>, expires_at: Option<DateTime<Utc>>) -> String {
+        let key = NewApiKey::generate();
+        self.state.db.create_api_key(user.id, api_key::Model {
+            id: 0,
+            user_id: user.id,
+            name: "test".to_string(),
+            prefix: String::new(),
+            key_hash: String::new(),
+            scopes: Scopes(scopes),
+            expires_at,
+            last_used_at: None,
+            created_at: Default::default(),
+        }, key.prefix, key.hash).await.unwrap();
+        key.key
+    }
+
+    pub async fn send(&self, method: Method, uri: &str, credential: Option<(&str, String)>, body: Option<Value>) -> (StatusCode, Value) {
+        let body = body.map(|body| ("application/json", body.to_string()));
+        self.send_raw(method, uri, credential, body).await
+    }
+
+    /// Send a body of any content type, `(content_type, body)`, and read the JSON response.
+    pub async fn send_raw(&self, method: Method, uri: &str, credential: Option<(&str, String)>, body: Option<(&str, String)>) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some((name, value)) = credential {
+            request = request.header(name, value);
+        }
+        let request = match body {
+            Some((content_type, body)) => request
+                .header(header::CONTENT_TYPE, content_type)
+                .body(Body::from(body)),
+            None => request.body(Body::empty()),
+        }.unwrap();
+
+        let response = self.app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+}
+
+pub fn bearer(token: &str) -> Option<(&'static str, String)> {
+    Some(("authorization", format!("Bearer {}", token)))
+}
+
+pub fn api_key_header(key: &str) -> Option<(&'static str, String)> {
+    Some(("x-api-key", key.to_string()))
+}
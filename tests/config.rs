@@ -0,0 +1,94 @@
+//! Loading the configuration from a file and the environment.
+
+use std::env;
+use std::fs;
+use std::sync::Mutex;
+
+use wamserver::config::{ConfigError, WamConfig};
+
+/// The environment is shared by the whole test binary, loads must not overlap.
+static ENV: Mutex<()> = Mutex::new(());
+
+const VARS: &[&str] = &[
+    "WAM_CONFIG", "WAM_BIND_ADDRESS", "DATABASE_URL", "DATABASE_ON_USER_DELETE", "KAFKA_ENABLED",
+    "SYTRAL_ENABLED", "SYTRAL_PASSWORD", "WAM_JWT_SECRET", "WAM_ACCESS_TOKEN_TTL_SECS",
+];
+
+/// Load `file` with the given environment variables, the integrations off unless they say otherwise.
+fn load(file: &str, vars: &[(&str, &str)]) -> Result<WamConfig, ConfigError> {
+    let _guard = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    let path = env::temp_dir().join(format!("wam-config-{}-{:?}.toml", std::process::id(), std::thread::current().id()));
+    fs::write(&path, file).unwrap();
+    // SAFETY: the lock keeps the other tests of this binary from touching the environment meanwhile
+    unsafe {
+        for var in VARS {
+            env::remove_var(var);
+        }
+        env::set_var("WAM_CONFIG", &path);
+        env::set_var("KAFKA_ENABLED", "false");
+        env::set_var("SYTRAL_ENABLED", "false");
+        for (var, value) in vars {
+            env::set_var(var, value);
+        }
+    }
+    let config = WamConfig::load();
+    fs::remove_file(&path).unwrap();
+    config
+}
+
+#[test]
+fn environment_wins_over_the_file() {
+    let config = load(
+        "[server]\nbind_address = \"127.0.0.1:3000\"\n[database]\nurl = \"sqlite://file.db\"\n",
+        &[("WAM_BIND_ADDRESS", "127.0.0.1:4000"), ("WAM_ACCESS_TOKEN_TTL_SECS", "60")],
+    ).unwrap();
+
+    assert_eq!(config.server.bind_address, "127.0.0.1:4000");
+    assert_eq!(config.database.url, "sqlite://file.db");
+    assert_eq!(config.auth.access_token_ttl_secs, 60);
+}
+
+#[test]
+fn all_problems_are_reported_together() {
+    let err = load(
+        "[auth]\njwt_secret = \"short\"\n",
+        &[("WAM_ACCESS_TOKEN_TTL_SECS", "soon"), ("DATABASE_ON_USER_DELETE", "archive"), ("WAM_BIND_ADDRESS", "nowhere")],
+    ).unwrap_err();
+
+    let problems = err.problems.join("\n");
+    for expected in ["WAM_ACCESS_TOKEN_TTL_SECS", "DATABASE_ON_USER_DELETE", "'nowhere'", "at least 32 bytes"] {
+        assert!(problems.contains(expected), "{expected} missing from:\n{problems}");
+    }
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    let err = load("[server]\nbind_adress = \"127.0.0.1:3000\"\n", &[]).unwrap_err();
+
+    assert!(err.problems.iter().any(|p| p.contains("bind_adress")), "{:?}", err.problems);
+}
+
+#[test]
+fn only_enabled_integrations_need_settings() {
+    let file = "[database]\nurl = \"sqlite://file.db\"\n";
+    assert!(load(file, &[]).is_ok());
+
+    if cfg!(feature = "sytral") {
+        let err = load(file, &[("SYTRAL_ENABLED", "true")]).unwrap_err();
+        assert!(err.problems.iter().any(|p| p.contains("SYTRAL_PASSWORD")), "{:?}", err.problems);
+    }
+}
+
+#[test]
+fn debug_output_redacts_secrets() {
+    let secret = "a-jwt-secret-long-enough-for-validation";
+    let config = load(
+        "[database]\nurl = \"sqlite://file.db\"\n",
+        &[("WAM_JWT_SECRET", secret), ("SYTRAL_PASSWORD", "hunter22")],
+    ).unwrap();
+
+    let debug = format!("{:?}", config);
+    assert!(!debug.contains(secret), "{debug}");
+    assert!(!debug.contains("hunter22"), "{debug}");
+    assert!(debug.contains("<redacted>"), "{debug}");
+}
@@ -0,0 +1,27 @@
+//! Problem details returned for failed requests.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use entity::user::Role;
+use serde_json::json;
+
+use common::{bearer, TestApp};
+
+#[tokio::test]
+async fn conflicts_do_not_leak_database_errors() {
+    let app = TestApp::new().await;
+    let admin = app.user("Admin", Role::Admin).await;
+    let token = app.token(&admin);
+
+    app.send(Method::POST, "/api/channel", bearer(&token), Some(json!({"name": "general"}))).await;
+    let (status, problem) = app.send(Method::POST, "/api/channel", bearer(&token), Some(json!({"name": "general"}))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(problem["code"], "conflict");
+    assert_eq!(problem["detail"], "A channel named general already exists");
+
+    let other = app.user("Other", Role::Member).await;
+    let (status, problem) = app.send(Method::PATCH, &format!("/api/user/{}", other.id), bearer(&token), Some(json!({"email": "admin@example.com"}))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(problem["detail"], "A user with email admin@example.com already exists");
+}
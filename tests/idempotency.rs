@@ -0,0 +1,91 @@
+//! Safe retries of `POST /api/message` with an idempotency key.
+
+mod common;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Method, Request, StatusCode};
+use entity::user::Role;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use common::{bearer, TestApp};
+
+/// Post a message with an `Idempotency-Key` header, telling whether the answer was replayed.
+async fn post_with_key(app: &TestApp, token: &str, key: &str, body: Value) -> (StatusCode, bool, Value) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/message")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header("Idempotency-Key", key)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let replayed = response.headers().get("idempotent-replayed").is_some_and(|v| v == "true");
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, replayed, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+async fn count_messages(app: &TestApp) -> usize {
+    let (_, page) = app.send(Method::GET, "/api/message", None, None).await;
+    page["messages"].as_array().unwrap().len()
+}
+
+#[tokio::test]
+async fn retries_return_the_original_message() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let token = app.token(&alice);
+
+    let (status, replayed, first) = post_with_key(&app, &token, "retry-1", json!({"text": "hello"})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!replayed);
+
+    // The retry body is ignored, the stored message is returned as is
+    let (status, replayed, again) = post_with_key(&app, &token, "retry-1", json!({"text": "hello, again"})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(replayed);
+    assert_eq!(again["id"], first["id"]);
+    assert_eq!(again["text"], "hello");
+    assert_eq!(count_messages(&app).await, 1);
+
+    // The body field is the same key as the header
+    let (_, replayed, from_field) = post_with_key(&app, &token, "retry-2", json!({"text": "other"})).await;
+    assert!(!replayed);
+    let (status, message) = app.send(Method::POST, "/api/message", bearer(&token), Some(json!({"text": "other", "client_msg_id": "retry-2"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(message["id"], from_field["id"]);
+    assert_eq!(count_messages(&app).await, 2);
+}
+
+#[tokio::test]
+async fn keys_are_scoped_to_their_author() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let bob = app.user("Bob", Role::Member).await;
+
+    let (_, _, from_alice) = post_with_key(&app, &app.token(&alice), "shared", json!({"text": "from alice"})).await;
+    let (status, replayed, from_bob) = post_with_key(&app, &app.token(&bob), "shared", json!({"text": "from bob"})).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(!replayed);
+    assert_ne!(from_bob["id"], from_alice["id"]);
+    assert_eq!(from_bob["user_id"], bob.id);
+    assert_eq!(count_messages(&app).await, 2);
+}
+
+#[tokio::test]
+async fn keys_must_have_a_valid_length() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let token = app.token(&alice);
+
+    for key in [String::new(), "k".repeat(256)] {
+        let (status, _, problem) = post_with_key(&app, &token, &key, json!({"text": "hello"})).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(problem["code"], "validation_failed");
+    }
+    assert_eq!(count_messages(&app).await, 0);
+}
@@ -0,0 +1,88 @@
+//! Cursor pagination of `GET /api/message` and `GET /api/user`.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use entity::user::Role;
+use serde_json::Value;
+
+use common::{bearer, TestApp};
+
+fn ids(page: &Value, key: &str) -> Vec<i64> {
+    page[key].as_array().unwrap().iter().map(|m| m["id"].as_i64().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn message_cursors_walk_both_ways() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let mut all = Vec::new();
+    for i in 0..5 {
+        all.push(app.message(&alice, &format!("message {}", i), None, None).await.id as i64);
+    }
+    let [m1, m2, m3, m4, m5] = all[..] else { unreachable!() };
+
+    // Newest first, the first page has nothing newer
+    let (status, page) = app.send(Method::GET, "/api/message?limit=2", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(ids(&page, "messages"), vec![m5, m4]);
+    assert_eq!(page["next_cursor"], m4);
+    assert_eq!(page["prev_cursor"], Value::Null);
+
+    // New messages do not shift the pages already handed out
+    app.message(&alice, "late", None, None).await;
+
+    let (_, page) = app.send(Method::GET, &format!("/api/message?limit=2&before={}", m4), None, None).await;
+    assert_eq!(ids(&page, "messages"), vec![m3, m2]);
+    assert_eq!(page["next_cursor"], m2);
+    assert_eq!(page["prev_cursor"], m3);
+
+    let (_, page) = app.send(Method::GET, &format!("/api/message?limit=2&before={}", m2), None, None).await;
+    assert_eq!(ids(&page, "messages"), vec![m1]);
+    assert_eq!(page["next_cursor"], Value::Null);
+    assert_eq!(page["prev_cursor"], m1);
+
+    // Going back up gives the messages right after the cursor, still newest first
+    let (_, page) = app.send(Method::GET, &format!("/api/message?limit=2&after={}", m1), None, None).await;
+    assert_eq!(ids(&page, "messages"), vec![m3, m2]);
+    assert_eq!(page["next_cursor"], m2);
+    assert_eq!(page["prev_cursor"], m3);
+}
+
+#[tokio::test]
+async fn page_sizes_are_bounded() {
+    let app = TestApp::new().await;
+
+    for uri in ["/api/message?limit=0", "/api/message?limit=1001"] {
+        let (status, problem) = app.send(Method::GET, uri, None, None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{uri}");
+        assert_eq!(problem["code"], "validation_failed");
+    }
+}
+
+#[tokio::test]
+async fn user_cursors_follow_ids() {
+    let app = TestApp::new().await;
+    let admin = app.user("Admin", Role::Admin).await;
+    let token = app.token(&admin);
+    for name in ["Bob", "Carol", "Dave"] {
+        app.user(name, Role::Member).await;
+    }
+
+    let mut seen = Vec::new();
+    let mut uri = "/api/user?limit=2".to_string();
+    loop {
+        let (status, page) = app.send(Method::GET, &uri, bearer(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        seen.extend(ids(&page, "users"));
+        match page["next_cursor"].as_i64() {
+            Some(cursor) => uri = format!("/api/user?limit=2&after={}", cursor),
+            None => break,
+        }
+    }
+
+    let mut sorted = seen.clone();
+    sorted.sort();
+    assert_eq!(seen, sorted);
+    assert_eq!(seen.len(), 4);
+}
@@ -0,0 +1,63 @@
+//! Route guards of the user roles.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use entity::api_key::Scope;
+use entity::user::Role;
+use serde_json::json;
+
+use common::{api_key_header, bearer, TestApp};
+
+#[tokio::test]
+async fn require_writer_rejects_anonymous_and_read_only_users() {
+    let app = TestApp::new().await;
+    let reader = app.user("Reader", Role::ReadOnly).await;
+    let member = app.user("Member", Role::Member).await;
+    let message = json!({"text": "hello"});
+
+    let (status, _) = app.send(Method::POST, "/api/message", None, Some(message.clone())).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app.send(Method::POST, "/api/message", bearer(&app.token(&reader)), Some(message.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, created) = app.send(Method::POST, "/api/message", bearer(&app.token(&member)), Some(message)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(created["user_id"], member.id);
+}
+
+#[tokio::test]
+async fn require_admin_needs_the_role_and_the_scope() {
+    let app = TestApp::new().await;
+    let member = app.user("Member", Role::Member).await;
+    let admin = app.user("Admin", Role::Admin).await;
+    let user_read_key = app.api_key(&admin, vec![Scope::UserRead], None).await;
+    let admin_key = app.api_key(&admin, vec![Scope::Admin], None).await;
+
+    let (status, _) = app.send(Method::POST, "/api/channel", None, Some(json!({"name": "a"}))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app.send(Method::POST, "/api/channel", bearer(&app.token(&member)), Some(json!({"name": "a"}))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.send(Method::POST, "/api/channel", api_key_header(&user_read_key), Some(json!({"name": "a"}))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = app.send(Method::POST, "/api/channel", bearer(&app.token(&admin)), Some(json!({"name": "a"}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = app.send(Method::POST, "/api/channel", api_key_header(&admin_key), Some(json!({"name": "b"}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn the_last_admin_cannot_be_demoted() {
+    let app = TestApp::new().await;
+    let admin = app.user("Admin", Role::Admin).await;
+    let uri = format!("/api/user/{}/role", admin.id);
+
+    let (status, _) = app.send(Method::PUT, &uri, bearer(&app.token(&admin)), Some(json!({"role": "member"}))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let other = app.user("Other", Role::Admin).await;
+    let (status, demoted) = app.send(Method::PUT, &uri, bearer(&app.token(&other)), Some(json!({"role": "member"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(demoted["role"], "member");
+}
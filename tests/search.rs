@@ -0,0 +1,21 @@
+//! Full-text search of messages.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use entity::user::Role;
+
+use common::TestApp;
+
+#[tokio::test]
+async fn snippets_escape_the_text_around_the_matches() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let message = app.message(&alice, "<img src=x onerror=alert(1)> hello & bye", None, None).await;
+
+    let (status, found) = app.send(Method::GET, "/api/message/search?q=hello", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let result = &found["results"][0];
+    assert_eq!(result["id"], message.id);
+    assert_eq!(result["snippet"], "&lt;img src=x onerror=alert(1)&gt; <mark>hello</mark> &amp; bye");
+}
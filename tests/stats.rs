@@ -0,0 +1,78 @@
+//! Message statistics of `GET /api/info`.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use chrono::{DateTime, Utc};
+use entity::user::Role;
+use serde_json::{json, Value};
+
+use common::{bearer, TestApp};
+
+fn at(time: &str) -> DateTime<Utc> {
+    format!("2026-10-18T{}Z", time).parse().unwrap()
+}
+
+fn counts(stats: &Value) -> Vec<(String, i64)> {
+    stats["rate"]["counts"].as_array().unwrap().iter()
+        .map(|b| (b["start"].as_str().unwrap().to_string(), b["count"].as_i64().unwrap()))
+        .collect()
+}
+
+#[tokio::test]
+async fn rates_are_bucketed_over_a_half_open_range() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    for time in ["10:00:05", "10:00:50", "10:01:10", "10:03:00"] {
+        let message = app.message(&alice, time, None, None).await;
+        app.backdate(&message, at(time)).await;
+    }
+
+    // `since` is rounded down to its bucket, empty buckets are listed
+    let (status, stats) = app.send(Method::GET, "/api/info?bucket=minute&since=2026-10-18T10:00:30Z&until=2026-10-18T10:04:00Z", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stats["rate"]["since"], "2026-10-18T10:00:00Z");
+    assert_eq!(counts(&stats), vec![
+        ("2026-10-18T10:00:00Z".to_string(), 2),
+        ("2026-10-18T10:01:00Z".to_string(), 1),
+        ("2026-10-18T10:02:00Z".to_string(), 0),
+        ("2026-10-18T10:03:00Z".to_string(), 1),
+    ]);
+
+    // `until` is exclusive
+    let (_, stats) = app.send(Method::GET, "/api/info?bucket=minute&since=2026-10-18T10:00:00Z&until=2026-10-18T10:03:00Z", None, None).await;
+    assert_eq!(counts(&stats).iter().map(|(_, count)| *count).collect::<Vec<_>>(), vec![2, 1, 0]);
+
+    let (_, stats) = app.send(Method::GET, "/api/info?bucket=hour&since=2026-10-18T10:00:00Z&until=2026-10-18T11:00:00Z", None, None).await;
+    assert_eq!(counts(&stats), vec![("2026-10-18T10:00:00Z".to_string(), 4)]);
+
+    let (status, _) = app.send(Method::GET, "/api/info?bucket=second&since=2026-10-18T10:00:00Z&until=2026-10-18T11:00:00Z", None, None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = app.send(Method::GET, "/api/info?since=2026-10-18T11:00:00Z&until=2026-10-18T10:00:00Z", None, None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn counts_only_cover_the_messages_the_caller_may_see() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let bob = app.user("Bob", Role::Member).await;
+    let private = app.channel("private", &[&bob]).await;
+
+    app.send(Method::POST, "/api/message", bearer(&app.token(&alice)), Some(json!({"text": "public"}))).await;
+    for _ in 0..2 {
+        app.send(Method::POST, "/api/message", bearer(&app.token(&bob)), Some(json!({"text": "secret", "channel_id": private.id}))).await;
+    }
+
+    let (_, stats) = app.send(Method::GET, "/api/info", None, None).await;
+    assert_eq!(stats["nb"], 1);
+    assert_eq!(stats["users"], json!([{"user_id": alice.id, "count": 1}]));
+    assert_eq!(stats["ingested"]["http"], 1);
+    assert_eq!(stats["rate"]["counts"].as_array().unwrap().iter().map(|b| b["count"].as_i64().unwrap()).sum::<i64>(), 1);
+
+    // Members see their channels, most active users first
+    let (_, stats) = app.send(Method::GET, "/api/info", bearer(&app.token(&bob)), None).await;
+    assert_eq!(stats["nb"], 3);
+    assert_eq!(stats["users"], json!([{"user_id": bob.id, "count": 2}, {"user_id": alice.id, "count": 1}]));
+    assert_eq!(stats["ingested"]["http"], 3);
+}
@@ -0,0 +1,69 @@
+//! Replies and the threads they form.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use entity::user::Role;
+use serde_json::{json, Value};
+
+use common::{bearer, TestApp};
+
+fn ids_and_depths(thread: &Value) -> Vec<(i64, u64)> {
+    thread["messages"].as_array().unwrap().iter()
+        .map(|m| (m["id"].as_i64().unwrap(), m["depth"].as_u64().unwrap()))
+        .collect()
+}
+
+#[tokio::test]
+async fn threads_list_replies_depth_first_from_the_root() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+    let token = app.token(&alice);
+
+    let root = app.message(&alice, "root", None, None).await;
+    let first = app.message(&alice, "first", Some(root.id), None).await;
+    let second = app.message(&alice, "second", Some(root.id), None).await;
+    let nested = app.message(&alice, "nested", Some(first.id), None).await;
+
+    // Any message of the thread gives the whole thread
+    let (status, thread) = app.send(Method::GET, &format!("/api/message/{}/thread", nested.id), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(thread["root_id"], root.id);
+    assert_eq!(ids_and_depths(&thread), vec![
+        (root.id as i64, 0),
+        (first.id as i64, 1),
+        (nested.id as i64, 2),
+        (second.id as i64, 1),
+    ]);
+    assert_eq!(thread["messages"][2]["parent_id"], first.id);
+
+    // Replies over HTTP join the thread, deleting a message makes its replies new threads
+    let (status, reply) = app.send(Method::POST, "/api/message", bearer(&token), Some(json!({"text": "late", "parent_id": second.id}))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.send(Method::DELETE, &format!("/api/message/{}", first.id), bearer(&token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, thread) = app.send(Method::GET, &format!("/api/message/{}/thread", root.id), None, None).await;
+    assert_eq!(ids_and_depths(&thread), vec![(root.id as i64, 0), (second.id as i64, 1), (reply["id"].as_i64().unwrap(), 2)]);
+    let (_, thread) = app.send(Method::GET, &format!("/api/message/{}/thread", nested.id), None, None).await;
+    assert_eq!(ids_and_depths(&thread), vec![(nested.id as i64, 0)]);
+}
+
+#[tokio::test]
+async fn long_reply_chains_are_returned() {
+    let app = TestApp::new().await;
+    let alice = app.user("Alice", Role::Member).await;
+
+    let root = app.message(&alice, "root", None, None).await;
+    let mut parent = root.id;
+    for i in 0..5000 {
+        parent = app.message(&alice, &format!("reply {}", i), Some(parent), None).await.id;
+    }
+
+    let (status, thread) = app.send(Method::GET, &format!("/api/message/{}/thread", root.id), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let messages = thread["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 5001);
+    assert_eq!(messages[5000]["id"], parent);
+    assert_eq!(messages[5000]["depth"], 5000);
+}
@@ -0,0 +1,151 @@
+//! User management: writes, deletion policies and the tombstone user.
+
+mod common;
+
+use axum::extract::ws::Message;
+use axum::http::{Method, StatusCode};
+use entity::user::Role;
+use serde_json::{json, Value};
+
+use wamserver::config::{OnUserDelete, WamConfig};
+
+use common::{bearer, TestApp};
+
+#[tokio::test]
+async fn the_tombstone_user_cannot_be_changed() {
+    let app = TestApp::new().await;
+    let admin = app.user("Admin", Role::Admin).await;
+    let alice = app.user("Alice", Role::Member).await;
+    let token = app.token(&admin);
+    app.send(Method::POST, "/api/message", bearer(&app.token(&alice)), Some(json!({"text": "hello"}))).await;
+
+    let (status, _) = app.send(Method::DELETE, &format!("/api/user/{}?messages=tombstone", alice.id), bearer(&token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, messages) = app.send(Method::GET, "/api/message", None, None).await;
+    let tombstone = messages["messages"][0]["user_id"].as_i64().unwrap();
+    let uri = format!("/api/user/{}", tombstone);
+
+    // Renaming it would not help, it is found by its flag
+    let (status, _) = app.send(Method::PATCH, &uri, bearer(&token), Some(json!({"email": "someone@example.com"}))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = app.send(Method::PUT, &uri, bearer(&token), Some(json!({"name": "Someone", "email": "someone@example.com"}))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = app.send(Method::PUT, &format!("{}/role", uri), bearer(&token), Some(json!({"role": "admin"}))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = app.send(Method::DELETE, &uri, bearer(&token), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (_, user) = app.send(Method::GET, &uri, bearer(&token), None).await;
+    assert_eq!(user["tombstone"], true);
+    assert_eq!(user["role"], "read_only");
+}
+
+#[tokio::test]
+async fn users_are_written_through_their_own_fields_only() {
+    let app = TestApp::new().await;
+    let admin = app.user("Admin", Role::Admin).await;
+    let token = app.token(&admin);
+    let mut events = app.state.ws_sender.subscribe();
+
+    let (status, created) = app.send(Method::POST, "/api/user", bearer(&token), Some(json!({"name": "Carol", "email": "carol@example.com", "password": "password1", "role": "read_only"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(created["role"], "read_only");
+    assert!(created.get("password").is_none() && created.get("password_hash").is_none());
+    let uri = format!("/api/user/{}", created["id"]);
+
+    // Every client gets the event, without the email
+    let event = events.try_recv().unwrap();
+    let Message::Text(frame) = event.frame else { panic!("expected a text frame") };
+    let event: Value = serde_json::from_str(&frame).unwrap();
+    assert_eq!(event["msg_type"], "user_created");
+    assert_eq!(event["message"], json!({"id": created["id"], "name": "Carol", "role": "read_only", "tombstone": false}));
+
+    let (status, _) = app.send(Method::POST, "/api/user", bearer(&token), Some(json!({"name": "Dan", "email": "dan@example.com", "id": 99}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // The role is not silently dropped from a replacement, it is refused
+    let (status, _) = app.send(Method::PUT, &uri, bearer(&token), Some(json!({"name": "Carol", "email": "carol@example.com", "role": "admin"}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, replaced) = app.send(Method::PUT, &uri, bearer(&token), Some(json!({"name": "Caroline", "email": "caroline@example.com"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(replaced["name"], "Caroline");
+    assert_eq!(replaced["role"], "read_only");
+}
+
+fn authors(page: &Value) -> Vec<(String, i64)> {
+    page["messages"].as_array().unwrap().iter()
+        .map(|m| (m["text"].as_str().unwrap().to_string(), m["user_id"].as_i64().unwrap()))
+        .collect()
+}
+
+#[tokio::test]
+async fn users_with_messages_are_kept_by_default() {
+    let app = TestApp::new().await;
+    let admin = app.user("Admin", Role::Admin).await;
+    let alice = app.user("Alice", Role::Member).await;
+    let bob = app.user("Bob", Role::Member).await;
+    let token = app.token(&admin);
+    app.message(&alice, "hello", None, None).await;
+
+    let (status, problem) = app.send(Method::DELETE, &format!("/api/user/{}", alice.id), bearer(&token), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(problem["code"], "conflict");
+    let (status, _) = app.send(Method::GET, &format!("/api/user/{}", alice.id), bearer(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Nothing to protect without messages
+    let (status, _) = app.send(Method::DELETE, &format!("/api/user/{}", bob.id), bearer(&token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = app.send(Method::GET, &format!("/api/user/{}", bob.id), bearer(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn cascading_deletes_remove_the_messages() {
+    let mut config = WamConfig::default();
+    config.database.on_user_delete = OnUserDelete::Cascade;
+    let app = TestApp::with_config(config).await;
+    let admin = app.user("Admin", Role::Admin).await;
+    let alice = app.user("Alice", Role::Member).await;
+    let bob = app.user("Bob", Role::Member).await;
+    let root = app.message(&alice, "question", None, None).await;
+    let answer = app.message(&bob, "answer", Some(root.id), None).await;
+
+    // The configured policy applies without a query parameter
+    let (status, _) = app.send(Method::DELETE, &format!("/api/user/{}", alice.id), bearer(&app.token(&admin)), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, page) = app.send(Method::GET, "/api/message", None, None).await;
+    assert_eq!(authors(&page), vec![("answer".to_string(), bob.id as i64)]);
+    let (_, thread) = app.send(Method::GET, &format!("/api/message/{}/thread", answer.id), None, None).await;
+    assert_eq!(thread["root_id"], answer.id);
+}
+
+#[tokio::test]
+async fn tombstoned_messages_share_one_deleted_user() {
+    let app = TestApp::new().await;
+    let admin = app.user("Admin", Role::Admin).await;
+    let alice = app.user("Alice", Role::Member).await;
+    let bob = app.user("Bob", Role::Member).await;
+    let token = app.token(&admin);
+    app.message(&alice, "from alice", None, None).await;
+    app.message(&bob, "from bob", None, None).await;
+    let mut events = app.state.ws_sender.subscribe();
+
+    for user in [&alice, &bob] {
+        let (status, _) = app.send(Method::DELETE, &format!("/api/user/{}?messages=tombstone", user.id), bearer(&token), None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    let (_, page) = app.send(Method::GET, "/api/message", None, None).await;
+    let authors = authors(&page);
+    let tombstone = authors[0].1;
+    assert_eq!(authors, vec![("from bob".to_string(), tombstone), ("from alice".to_string(), tombstone)]);
+    assert!(![alice.id as i64, bob.id as i64].contains(&tombstone));
+
+    let event = events.try_recv().unwrap();
+    let Message::Text(frame) = event.frame else { panic!("expected a text frame") };
+    let event: Value = serde_json::from_str(&frame).unwrap();
+    assert_eq!(event["msg_type"], "user_deleted");
+    assert_eq!(event["message"], json!({"id": alice.id, "messages": "tombstone", "reassigned_to": tombstone}));
+}
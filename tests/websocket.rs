@@ -0,0 +1,66 @@
+//! WebSocket sessions, over a real listener.
+
+mod common;
+
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use entity::user::Role;
+use futures::StreamExt;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message;
+use wamserver::config::WamConfig;
+
+use common::{bearer, TestApp};
+
+/// Serve the app on a free port and open a WebSocket session with the token.
+async fn connect(app: &TestApp, token: &str) -> tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(axum::serve(listener, app.app.clone()).into_future());
+
+    let (socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/api/ws?token={}", address, token)).await.unwrap();
+    // The session is registered once the upgrade is handled
+    while app.state.ws_connections.lock().unwrap().is_empty() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    socket
+}
+
+/// The reason of the Close frame ending the session, skipping the events sent before it.
+async fn close_reason(socket: &mut (impl StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin)) -> String {
+    let closed = tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(Ok(message)) = socket.next().await {
+            if let Message::Close(Some(frame)) = message {
+                return frame;
+            }
+        }
+        panic!("the session ended without a Close frame");
+    });
+    let frame = closed.await.expect("the session was not closed");
+    assert_eq!(frame.code, CloseCode::Policy);
+    frame.reason.to_string()
+}
+
+#[tokio::test]
+async fn sessions_of_deleted_users_are_closed() {
+    let app = TestApp::new().await;
+    let admin = app.user("Admin", Role::Admin).await;
+    let alice = app.user("Alice", Role::Member).await;
+    let mut socket = connect(&app, &app.token(&alice)).await;
+
+    let (status, _) = app.send(Method::DELETE, &format!("/api/user/{}", alice.id), bearer(&app.token(&admin)), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(close_reason(&mut socket).await, "Session revoked");
+}
+
+#[tokio::test]
+async fn sessions_end_with_their_token() {
+    let mut config = WamConfig::default();
+    config.auth.access_token_ttl_secs = 1;
+    let app = TestApp::with_config(config).await;
+    let alice = app.user("Alice", Role::Member).await;
+    let mut socket = connect(&app, &app.token(&alice)).await;
+
+    assert_eq!(close_reason(&mut socket).await, "Session expired");
+}